@@ -1,5 +1,7 @@
 use std::str::Bytes;
 
+use crate::parallel::Parallel;
+
 #[cfg(feature = "input")]
 const INPUT: &str = include_str!("../input/day01.txt");
 
@@ -13,6 +15,16 @@ pub fn solve_part_2() -> i64 {
     part_2(INPUT)
 }
 
+#[cfg(feature = "input")]
+pub fn solve_part_1_parallel(parallel: &Parallel) -> usize {
+    part_1_parallel(INPUT, parallel)
+}
+
+#[cfg(feature = "input")]
+pub fn solve_part_2_parallel(parallel: &Parallel) -> i64 {
+    part_2_parallel(INPUT, parallel)
+}
+
 fn parse_instruction(bytes: &mut Bytes<'_>) -> Option<i64> {
     let sign = match bytes.next()? {
         b'L' => -1,
@@ -35,11 +47,14 @@ fn parse_instruction(bytes: &mut Bytes<'_>) -> Option<i64> {
     Some(sign * n)
 }
 fn part_1(input: &str) -> usize {
+    part_1_from(input, 50)
+}
+
+fn part_1_from(input: &str, mut dial: i64) -> usize {
     let instructions = input
         .lines()
         .map(|s| parse_instruction(&mut s.bytes()).expect("Could not parse instruction!"));
     let mut count = 0;
-    let mut dial = 50;
     for turns in instructions {
         dial = (dial + turns) % (100);
         if dial == 0 {
@@ -50,11 +65,14 @@ fn part_1(input: &str) -> usize {
 }
 
 fn part_2(input: &str) -> i64 {
+    part_2_from(input, 50)
+}
+
+fn part_2_from(input: &str, mut dial: i64) -> i64 {
     let instructions = input
         .lines()
         .map(|s| parse_instruction(&mut s.bytes()).expect("Could not parse instruction!"));
     let mut count = 0;
-    let mut dial = 50;
     for turns in instructions {
         if turns.is_negative() {
             // "reverse" the dial for easier left turns
@@ -68,8 +86,50 @@ fn part_2(input: &str) -> i64 {
     count
 }
 
+/// Where the dial ends up after every instruction starting from 0
+fn net_turns(input: &str) -> i64 {
+    input
+        .lines()
+        .map(|s| parse_instruction(&mut s.bytes()).expect("Could not parse instruction!"))
+        .fold(0, |dial, turns| (dial + turns).rem_euclid(100))
+}
+
+/// Each instruction depends on where the dial was left, so the chunks first work out their net turns in parallel
+/// which gives every chunk its starting position, then count from there in parallel
+fn starting_dials<'a>(input: &'a str, parallel: &Parallel) -> Vec<(&'a str, i64)> {
+    let chunks = parallel.split(input, b'\n');
+    let turns = parallel.run(&chunks, |chunk| net_turns(chunk));
+    chunks
+        .into_iter()
+        .zip(turns)
+        .scan(50, |dial, (chunk, turns)| {
+            let start = *dial;
+            *dial = (*dial + turns).rem_euclid(100);
+            Some((chunk, start))
+        })
+        .collect()
+}
+
+fn part_1_parallel(input: &str, parallel: &Parallel) -> usize {
+    let chunks = starting_dials(input, parallel);
+    parallel
+        .run(&chunks, |&(chunk, dial)| part_1_from(chunk, dial))
+        .into_iter()
+        .sum()
+}
+
+fn part_2_parallel(input: &str, parallel: &Parallel) -> i64 {
+    let chunks = starting_dials(input, parallel);
+    parallel
+        .run(&chunks, |&(chunk, dial)| part_2_from(chunk, dial))
+        .into_iter()
+        .sum()
+}
+
 #[cfg(test)]
 mod test {
+    use std::num::NonZeroUsize;
+
     use super::*;
 
     const INPUT: &str = "L68\nL30\nR48\nL5\nR60\nL55\nL1\nL99\nR14\nL82";
@@ -85,4 +145,16 @@ mod test {
         let answer = part_2(INPUT);
         assert_eq!(answer, 6)
     }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let long = INPUT.repeat(50);
+        for threads in 1..=8 {
+            let parallel = Parallel::new(NonZeroUsize::new(threads).unwrap(), 0);
+            for input in [INPUT, long.as_str()] {
+                assert_eq!(part_1_parallel(input, &parallel), part_1(input));
+                assert_eq!(part_2_parallel(input, &parallel), part_2(input));
+            }
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use crate::parallel::Parallel;
+
 #[cfg(feature = "input")]
 const INPUT: &str = include_str!("../input/day02.txt");
 
@@ -14,6 +16,17 @@ pub fn solve_part_2() -> u64 {
     run_solution(INPUT, &[2, 3, 5, 7]) - run_solution(INPUT, &[6, 10])
 }
 
+#[cfg(feature = "input")]
+pub fn solve_part_1_parallel(parallel: &Parallel) -> u64 {
+    run_solution_parallel(INPUT, &[2], parallel)
+}
+
+#[cfg(feature = "input")]
+pub fn solve_part_2_parallel(parallel: &Parallel) -> u64 {
+    run_solution_parallel(INPUT, &[2, 3, 5, 7], parallel)
+        - run_solution_parallel(INPUT, &[6, 10], parallel)
+}
+
 const fn make_pow_10() -> [u64; 20] {
     let mut arr = [0u64; 20];
 
@@ -104,8 +117,20 @@ fn run_solution(input: &str, repititions: &[usize]) -> u64 {
         })
 }
 
+/// [`run_solution`] with the ranges split across threads at commas
+fn run_solution_parallel(input: &str, repititions: &[usize], parallel: &Parallel) -> u64 {
+    parallel.map_reduce(
+        input,
+        b',',
+        |chunk| run_solution(chunk, repititions),
+        |a, b| a + b,
+    )
+}
+
 #[cfg(test)]
 mod test {
+    use std::num::NonZeroUsize;
+
     use super::*;
 
     const INPUT: &str = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124";
@@ -130,4 +155,17 @@ mod test {
         let answer = run_solution(INPUT, &[2, 3, 5, 7]) - run_solution(INPUT, &[6, 10]);
         assert_eq!(answer, 4174379265)
     }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        for threads in 1..=12 {
+            let parallel = Parallel::new(NonZeroUsize::new(threads).unwrap(), 0);
+            for reps in [&[2][..], &[2, 3, 5, 7], &[6, 10]] {
+                assert_eq!(
+                    run_solution_parallel(INPUT, reps, &parallel),
+                    run_solution(INPUT, reps)
+                );
+            }
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use crate::parallel::Parallel;
+
 #[cfg(feature = "input")]
 const INPUT: &str = include_str!("../input/day03.txt");
 
@@ -21,67 +23,249 @@ pub fn solve_part_2() -> u64 {
     part_2(INPUT, LINE_SIZE)
 }
 
+#[cfg(feature = "input")]
+pub fn solve_part_1_parallel(parallel: &Parallel) -> u32 {
+    part_1_parallel(INPUT, parallel)
+}
+
+#[cfg(feature = "input")]
+pub fn solve_part_2_parallel(parallel: &Parallel) -> u64 {
+    part_2_parallel(INPUT, LINE_SIZE, parallel)
+}
+
+/// Number of batteries turned on per bank in part 2
+const BATTERIES: usize = 12;
+
+/// Value of an ascii digit, anything else sets `invalid` without branching so lines are only checked once
+#[inline(always)]
+fn to_digit(byte: u8, invalid: &mut bool) -> u8 {
+    let digit = byte.wrapping_sub(b'0');
+    *invalid |= digit > 9;
+    digit
+}
+
 fn part_1(input: &str) -> u32 {
-    input
-        .lines()
-        .map(|line| {
-            line.chars().map(|ch| {
-                ch.to_digit(10)
-                    .expect("Invalid input! Expecting a series of lines with only digits!")
-            })
-        })
-        .fold(0, |sum, line| {
-            let mut digits: Vec<_> = line.collect();
-            let last_digit: u32 = digits.pop().expect("Invalid input! Empty Line!");
-            let (tens, ones) = digits.iter().fold((0, last_digit), |(first, snd), ch| {
-                let c = *ch;
-                if c > first {
-                    (*ch, last_digit)
-                } else if c > snd {
-                    (first, c)
-                } else {
-                    (first, snd)
-                }
-            });
-            sum + tens * 10 + ones
-        })
+    input.lines().fold(0, |sum, line| {
+        let (&last, rest) = line
+            .as_bytes()
+            .split_last()
+            .expect("Invalid input! Empty Line!");
+        let mut invalid = false;
+        let last_digit = to_digit(last, &mut invalid);
+        let (tens, ones) = rest.iter().fold((0, last_digit), |(first, snd), &byte| {
+            let c = to_digit(byte, &mut invalid);
+            if c > first {
+                (c, last_digit)
+            } else if c > snd {
+                (first, c)
+            } else {
+                (first, snd)
+            }
+        });
+        assert!(
+            !invalid,
+            "Invalid input! Expecting a series of lines with only digits!"
+        );
+        sum + tens as u32 * 10 + ones as u32
+    })
 }
 
 fn part_2(input: &str, line_size: usize) -> u64 {
-    input
-        .lines()
-        .map(|line| {
-            line.chars().map(|ch| {
-                ch.to_digit(10)
-                    .expect("Invalid input! Expecting a series of lines with only digits!")
+    // only ever holds the digits we keep, a digit that doesn't fit is dropped straight away instead of being pushed
+    // and truncated later
+    let mut stack = [0u8; BATTERIES];
+    input.lines().fold(0, |sum, line| {
+        let mut to_remove = line_size - BATTERIES;
+        let mut len = 0;
+        let mut invalid = false;
+
+        for &byte in line.as_bytes() {
+            let digit = to_digit(byte, &mut invalid);
+            while len > 0 && to_remove > 0 && stack[len - 1] < digit {
+                len -= 1;
+                to_remove -= 1;
+            }
+            if len < BATTERIES {
+                stack[len] = digit;
+                len += 1;
+            } else {
+                // a bank longer than `line_size` runs out of removals, the rest are just cut off
+                to_remove = to_remove.saturating_sub(1);
+            }
+        }
+        assert!(
+            !invalid,
+            "Invalid input! Expecting a series of lines with only digits!"
+        );
+
+        sum + stack[..len]
+            .iter()
+            .fold(0, |sum, &digit| sum * 10 + digit as u64)
+    })
+}
+
+fn part_1_parallel(input: &str, parallel: &Parallel) -> u32 {
+    parallel.map_reduce(input, b'\n', part_1, |a, b| a + b)
+}
+
+fn part_2_parallel(input: &str, line_size: usize, parallel: &Parallel) -> u64 {
+    parallel.map_reduce(input, b'\n', |chunk| part_2(chunk, line_size), |a, b| a + b)
+}
+
+/// The best joltage of a bank for every number of batteries `k` at once
+///
+/// Repeatedly dropping the first digit that is smaller than its successor (or the last digit when there is none) is
+/// optimal for every `k`, so the best selections are nested and a single unbounded stack pass gives the order digits
+/// get dropped in. A digit is then part of the best selection for every `k` at or above its rank.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoltageTable {
+    digits: Vec<u8>,
+    /// smallest `k` whose best selection still contains the digit
+    ranks: Vec<usize>,
+}
+
+impl JoltageTable {
+    pub fn new(bank: &str) -> Self {
+        let digits: Vec<u8> = bank
+            .bytes()
+            .map(|b| {
+                let digit = b.wrapping_sub(b'0');
+                assert!(
+                    digit < 10,
+                    "Invalid input! Expecting a series of lines with only digits!"
+                );
+                digit
             })
-        })
-        .fold(0, |sum, line| {
-            let mut to_remove = line_size - 12;
-            let mut stack = Vec::with_capacity(12);
-
-            for digit in line {
-                while let Some(&last) = stack.last() {
-                    if to_remove > 0 && last < digit {
-                        stack.pop();
-                        to_remove -= 1;
-                    } else {
-                        break;
-                    }
+            .collect();
+
+        let n = digits.len();
+        let mut ranks = vec![0; n];
+        let mut removed = 0;
+        let mut stack: Vec<usize> = Vec::with_capacity(n);
+        for (i, &digit) in digits.iter().enumerate() {
+            while let Some(&last) = stack.last() {
+                if digits[last] < digit {
+                    stack.pop();
+                    // dropping this digit takes us from `n - removed` batteries to one fewer
+                    ranks[last] = n - removed;
+                    removed += 1;
+                } else {
+                    break;
                 }
-                stack.push(digit);
             }
-            stack.truncate(12);
+            stack.push(i);
+        }
+        // what's left is non-increasing, so the tail goes first
+        while let Some(last) = stack.pop() {
+            ranks[last] = n - removed;
+            removed += 1;
+        }
+
+        Self { digits, ranks }
+    }
+
+    /// Number of batteries in the bank, the largest `k` in the table
+    pub fn len(&self) -> usize {
+        self.digits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Digits of the best joltage using `k` batteries, most significant first
+    pub fn digits(&self, k: usize) -> impl Iterator<Item = u8> + '_ {
+        self.digits
+            .iter()
+            .zip(&self.ranks)
+            .filter(move |&(_, &rank)| rank <= k)
+            .map(|(&digit, _)| digit)
+    }
+
+    /// Best joltage using `k` batteries in decimal, `None` if `k` is out of range
+    ///
+    /// Full length banks make numbers far too big for any integer type, so they're kept as their digits.
+    pub fn joltage(&self, k: usize) -> Option<String> {
+        if k == 0 || k > self.len() {
+            return None;
+        }
+        Some(self.digits(k).map(|digit| (b'0' + digit) as char).collect())
+    }
 
-            sum + stack
-                .into_iter()
-                .fold(0, |sum, digit| sum * 10 + digit as u64)
+    /// Best joltage for every `k` from 1 to [`len`](Self::len) in decimal, indexed by `k - 1`
+    pub fn joltages(&self) -> Vec<String> {
+        (1..=self.len()).filter_map(|k| self.joltage(k)).collect()
+    }
+}
+
+/// Add the decimal number `digits` to `total`, both least significant digit first
+fn add_digits(total: &mut Vec<u8>, digits: impl Iterator<Item = u8>) {
+    let mut carry = 0;
+    let mut i = 0;
+    for digit in digits {
+        if i == total.len() {
+            total.push(0);
+        }
+        let sum = total[i] + digit + carry;
+        total[i] = sum % 10;
+        carry = sum / 10;
+        i += 1;
+    }
+    while carry > 0 {
+        if i == total.len() {
+            total.push(0);
+        }
+        let sum = total[i] + carry;
+        total[i] = sum % 10;
+        carry = sum / 10;
+        i += 1;
+    }
+}
+
+/// Total of the best joltages across every bank for each `k` in decimal, indexed by `k - 1`
+///
+/// Runs up to the length of the longest bank, `None` where a bank is too short to supply `k` batteries.
+pub fn total_joltages(input: &str) -> Vec<Option<String>> {
+    // least significant digit first, so carries can grow the number
+    let mut totals: Vec<Option<Vec<u8>>> = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let table = JoltageTable::new(line);
+        if table.len() > totals.len() {
+            // any bank we've already seen was too short for these
+            let fill = if i == 0 { Some(Vec::new()) } else { None };
+            totals.resize(table.len(), fill);
+        }
+        for (k, total) in totals.iter_mut().enumerate() {
+            if k >= table.len() {
+                *total = None;
+            } else if let Some(total) = total {
+                let mut digits: Vec<u8> = table.digits(k + 1).collect();
+                digits.reverse();
+                add_digits(total, digits.into_iter());
+            }
+        }
+    }
+    totals
+        .into_iter()
+        .map(|total| {
+            total.map(|digits| match digits.iter().rposition(|&d| d != 0) {
+                Some(top) => digits[..=top]
+                    .iter()
+                    .rev()
+                    .map(|&d| (b'0' + d) as char)
+                    .collect(),
+                None => "0".to_string(),
+            })
         })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroUsize;
+
     use super::*;
+    use crate::counting;
 
     const DATA: &str = "987654321111111\n811111111111119\n234234234234278\n818181911112111";
 
@@ -102,4 +286,107 @@ mod tests {
         };
         assert_eq!(part_2(DATA, TEST_SIZE), 3121910778619);
     }
+
+    /// best joltage of `k` batteries by running the stack algorithm from [`part_2`] for that `k`
+    fn stack_joltage(bank: &str, k: usize) -> u128 {
+        let mut to_remove = bank.len() - k;
+        let mut stack = Vec::with_capacity(k);
+        for digit in bank.bytes().map(|b| b - b'0') {
+            while let Some(&last) = stack.last() {
+                if to_remove > 0 && last < digit {
+                    stack.pop();
+                    to_remove -= 1;
+                } else {
+                    break;
+                }
+            }
+            stack.push(digit);
+        }
+        stack.truncate(k);
+        stack
+            .into_iter()
+            .fold(0, |sum, digit| sum * 10 + digit as u128)
+    }
+
+    #[test]
+    fn joltage_table_matches_stack() {
+        for bank in DATA.lines().chain(["0000", "1", "9081726354", "55545556"]) {
+            let table = JoltageTable::new(bank);
+            let joltages = table.joltages();
+            assert_eq!(joltages.len(), bank.len());
+            for k in 1..=bank.len() {
+                let expected = stack_joltage(bank, k);
+                let expected = format!("{expected:0k$}");
+                assert_eq!(table.joltage(k), Some(expected.clone()), "{bank} k={k}");
+                assert_eq!(joltages[k - 1], expected, "{bank} k={k}");
+            }
+            assert_eq!(table.joltage(0), None);
+            assert_eq!(table.joltage(bank.len() + 1), None);
+        }
+    }
+
+    #[test]
+    fn joltage_table_long_bank() {
+        let bank = "9".repeat(50) + "1";
+        let joltages = JoltageTable::new(&bank).joltages();
+        assert_eq!(joltages.len(), 51);
+        assert_eq!(joltages[38], "9".repeat(39));
+        assert_eq!(joltages[49], "9".repeat(50));
+        assert_eq!(joltages[50], bank);
+    }
+
+    #[test]
+    fn total_joltages_example() {
+        let totals = total_joltages(DATA);
+        assert_eq!(totals.len(), 15);
+        assert_eq!(totals[1], Some(part_1(DATA).to_string()));
+        assert_eq!(totals[11], Some("3121910778619".to_string()));
+        assert_eq!(
+            total_joltages("19\n123"),
+            [Some("12".to_string()), Some("42".to_string()), None]
+        );
+        assert_eq!(total_joltages("00\n0"), [Some("0".to_string()), None]);
+    }
+
+    #[test]
+    fn total_joltages_long_banks() {
+        // 10^k - 1 plus 10^(k - 1) carries all the way up into a new digit
+        let input = format!("{}\n1{}", "9".repeat(60), "0".repeat(59));
+        let totals = total_joltages(&input);
+        assert_eq!(totals.len(), 60);
+        for (k, total) in (1..).zip(&totals) {
+            assert_eq!(
+                total.as_deref(),
+                Some(format!("10{}", "9".repeat(k - 1)).as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        for threads in 1..=6 {
+            let parallel = Parallel::new(NonZeroUsize::new(threads).unwrap(), 0);
+            assert_eq!(part_1_parallel(DATA, &parallel), part_1(DATA));
+            assert_eq!(part_2_parallel(DATA, 15, &parallel), part_2(DATA, 15));
+        }
+    }
+
+    #[test]
+    fn solve_without_allocating() {
+        let before = counting::allocations();
+        let answers = (part_1(DATA), part_2(DATA, 15));
+        assert_eq!(counting::allocations(), before);
+        assert_eq!(answers, (357, 3121910778619));
+    }
+
+    #[test]
+    #[should_panic(expected = "only digits")]
+    fn part_2_rejects_non_digits() {
+        part_2("98765432111111x", 15);
+    }
+
+    #[test]
+    fn part_2_overlong_line() {
+        assert_eq!(part_2("1234567890123\n12345678901234", 13), 469135780246);
+    }
 }
@@ -1,92 +1,547 @@
-use std::{array, mem};
+use crate::grid::{Edges, Grid};
 
-#[cfg(feature = "input")]
-const INPUT: &str = include_str!("../input/day04.txt");
+use bitboard::BitGrid;
 
 #[cfg(feature = "input")]
-const INPUT_SIZE: usize = {
-    let bytes = INPUT.as_bytes();
-    let mut len = 0;
-    while len < bytes.len() && bytes[len] != b'\n' {
-        len += 1
-    }
-    len
-};
+const INPUT: &str = include_str!("../input/day04.txt");
 
 #[cfg(feature = "input")]
 pub fn solve_part_1() -> usize {
-    part_1::<INPUT_SIZE>(INPUT)
+    part_1(INPUT)
 }
 
 #[cfg(feature = "input")]
 pub fn solve_part_2() -> usize {
-    part_2::<INPUT_SIZE>(INPUT)
+    part_2(INPUT)
+}
+
+#[cfg(feature = "input")]
+pub fn solve_part_1_with(rule: &AccessRule) -> usize {
+    part_1_with(INPUT, rule)
 }
 
-#[derive(Debug)]
-struct Grid<const N: usize>([[u8; N]; N]);
+#[cfg(feature = "input")]
+pub fn solve_part_2_with(rule: &AccessRule) -> usize {
+    part_2_with(INPUT, rule)
+}
 
-impl<const N: usize> Grid<N> {
-    fn is_unblocked(&self, idx_line: usize, idx_byte: usize) -> bool {
-        let top = idx_line.checked_sub(1);
-        let left = idx_byte.checked_sub(1);
-        let bottom = (idx_line + 1 < N).then_some(idx_line + 1);
-        let right: Option<usize> = (idx_byte + 1 < N).then_some(idx_byte + 1);
+#[cfg(feature = "input")]
+pub fn solve_part_1_bitboard() -> usize {
+    part_1_bitboard(INPUT)
+}
 
-        let mut count = 0;
-        for maybe_i in [top, Some(idx_line), bottom] {
-            for maybe_j in [left, Some(idx_byte), right] {
-                if let (Some(i), Some(j)) = (maybe_i, maybe_j)
-                    && self.0[i][j] == b'@'
+#[cfg(feature = "input")]
+pub fn solve_part_2_bitboard() -> usize {
+    part_2_bitboard(INPUT)
+}
+
+#[cfg(feature = "input")]
+pub fn solve_waves(rule: &AccessRule) -> Waves {
+    waves(INPUT, rule)
+}
+
+/// Write a frame for every round of part 2 to `dir`, returning how many rounds there were
+#[cfg(feature = "input")]
+pub fn render_part_2(dir: &std::path::Path) -> std::io::Result<usize> {
+    render::write_frames(&waves(INPUT, &AccessRule::default()), dir)
+}
+
+/// The cells around a roll that can block it, as `(row, column)` offsets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// cells within this many steps along rows and columns (a diamond), 1 is the 4 sharing an edge
+    VonNeumann(usize),
+    /// cells within this many steps in any direction (a square), 1 is the surrounding 8
+    Moore(usize),
+    /// any other stencil, `(0, 0)` is ignored as the roll itself is covered by [`AccessRule::include_self`]
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighbourhood {
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        let square = |r: usize| {
+            let r = r as isize;
+            (-r..=r).flat_map(move |i| (-r..=r).map(move |j| (i, j)))
+        };
+        match self {
+            Self::VonNeumann(r) => square(*r)
+                .filter(|&(i, j)| i.unsigned_abs() + j.unsigned_abs() <= *r)
+                .filter(|&pos| pos != (0, 0))
+                .collect(),
+            Self::Moore(r) => square(*r).filter(|&pos| pos != (0, 0)).collect(),
+            Self::Custom(offsets) => offsets
+                .iter()
+                .copied()
+                .filter(|&pos| pos != (0, 0))
+                .collect(),
+        }
+    }
+}
+
+/// How many rolls it takes to block one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Threshold {
+    /// accessible with strictly fewer rolls than this
+    Below(usize),
+    /// accessible with this many rolls or fewer
+    AtMost(usize),
+}
+
+impl Threshold {
+    pub fn allows(&self, rolls: usize) -> bool {
+        match *self {
+            Self::Below(limit) => rolls < limit,
+            Self::AtMost(limit) => rolls <= limit,
+        }
+    }
+}
+
+/// When a forklift can get to a roll
+///
+/// The default is the puzzle's rule: fewer than 5 rolls in the 3x3 square centred on the roll, itself included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessRule {
+    pub neighbourhood: Neighbourhood,
+    pub include_self: bool,
+    pub threshold: Threshold,
+    /// what neighbours off the edge of the grid are, nothing by default
+    pub edges: Edges,
+}
+
+impl Default for AccessRule {
+    fn default() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::Moore(1),
+            include_self: true,
+            threshold: Threshold::Below(5),
+            edges: Edges::Empty,
+        }
+    }
+}
+
+impl AccessRule {
+    /// Whether a roll surrounded by `rolls` rolls in its neighbourhood, not counting itself, is accessible
+    pub fn allows(&self, rolls: usize) -> bool {
+        self.threshold.allows(rolls + self.include_self as usize)
+    }
+}
+
+/// Number of rolls in the neighbourhood of `(row, col)` given by `offsets`, with [`Edges::Full`] every neighbour off
+/// the grid is a roll
+fn count_rolls(
+    grid: &Grid<u8>,
+    row: usize,
+    col: usize,
+    offsets: &[(isize, isize)],
+    edges: Edges,
+) -> usize {
+    offsets
+        .iter()
+        .filter(|&&offset| match grid.resolve(row, col, offset, edges) {
+            Some(pos) => grid[pos] == b'@',
+            None => edges == Edges::Full,
+        })
+        .count()
+}
+
+fn parse(input: &str) -> Grid<u8> {
+    Grid::parse(input, |b| b).unwrap_or_else(|e| panic!("Malformed input! {e}"))
+}
+
+fn part_1(input: &str) -> usize {
+    part_1_with(input, &AccessRule::default())
+}
+
+fn part_1_with(input: &str, rule: &AccessRule) -> usize {
+    let grid = parse(input);
+    let offsets = rule.neighbourhood.offsets();
+    grid.positions()
+        .filter(|&(i, j)| {
+            grid[(i, j)] == b'@' && rule.allows(count_rolls(&grid, i, j, &offsets, rule.edges))
+        })
+        .count()
+}
+
+fn part_2(input: &str) -> usize {
+    part_2_with(input, &AccessRule::default())
+}
+
+/// Bookkeeping for taking rolls off the grid one at a time, keeping how many rolls surround each roll so removing a
+/// roll only has to update the rolls whose neighbourhood it's in
+struct Removal<'a> {
+    grid: Grid<u8>,
+    neighbours: Grid<u32>,
+    offsets: Vec<(isize, isize)>,
+    /// a custom stencil needn't be symmetric, without wrapping edges a roll is in the neighbourhood of the rolls at the
+    /// opposite offsets
+    reverse: Vec<(isize, isize)>,
+    rule: &'a AccessRule,
+}
+
+impl<'a> Removal<'a> {
+    /// Count the neighbours of every roll, also returning the rolls that are accessible straight away
+    ///
+    /// Rolls are taken off the grid as soon as they're queued so nothing gets queued twice.
+    fn new(mut grid: Grid<u8>, rule: &'a AccessRule) -> (Self, Vec<(usize, usize)>) {
+        let offsets = rule.neighbourhood.offsets();
+        let mut neighbours = Grid::new(grid.width(), grid.height(), 0u32);
+        let mut queue = Vec::new();
+        for (i, j) in grid.positions() {
+            if grid[(i, j)] != b'@' {
+                continue;
+            }
+            let count = count_rolls(&grid, i, j, &offsets, rule.edges);
+            neighbours[(i, j)] = count as u32;
+            if rule.allows(count) {
+                queue.push((i, j));
+            }
+        }
+        for &pos in &queue {
+            grid[pos] = b'.';
+        }
+
+        let reverse = offsets.iter().map(|&(i, j)| (-i, -j)).collect();
+        let removal = Self {
+            grid,
+            neighbours,
+            offsets,
+            reverse,
+            rule,
+        };
+        (removal, queue)
+    }
+
+    /// Remove a queued roll, queueing any rolls that become accessible because of it
+    fn remove(&mut self, (i, j): (usize, usize), queue: &mut Vec<(usize, usize)>) {
+        match self.rule.edges {
+            Edges::Empty | Edges::Full => {
+                for k in 0..self.reverse.len() {
+                    if let Some(n) = self.grid.resolve(i, j, self.reverse[k], Edges::Empty) {
+                        self.release(n, queue);
+                    }
+                }
+            }
+            // wrapping and mirroring can reach the roll from the far side of the grid
+            Edges::Wrap | Edges::Mirror => {
+                for n in self
+                    .grid
+                    .reverse_stencil(i, j, &self.offsets, self.rule.edges)
                 {
-                    count += 1;
+                    self.release(n, queue);
                 }
             }
         }
-        count < 5
     }
-}
 
-fn remove_accessible<const N: usize>(grid: &mut Grid<N>) -> usize {
-    let mut count = 0;
-    for i in 0..N {
-        for j in 0..N {
-            if grid.0[i][j] == b'@' && grid.is_unblocked(i, j) {
-                count += 1;
-                let _ = mem::replace(&mut grid.0[i][j], b'.');
+    /// One fewer roll around `pos`
+    fn release(&mut self, pos: (usize, usize), queue: &mut Vec<(usize, usize)>) {
+        if self.grid[pos] == b'@' {
+            self.neighbours[pos] -= 1;
+            if self.rule.allows(self.neighbours[pos] as usize) {
+                self.grid[pos] = b'.';
+                queue.push(pos);
             }
         }
     }
+}
+
+/// Rather than sweeping the whole grid until nothing changes, work through a queue of the rolls that just became
+/// accessible
+fn part_2_with(input: &str, rule: &AccessRule) -> usize {
+    let (mut removal, mut queue) = Removal::new(parse(input), rule);
+    let mut count = 0;
+    while let Some(pos) = queue.pop() {
+        count += 1;
+        removal.remove(pos, &mut queue);
+    }
     count
 }
 
-fn parse<const N: usize>(input: &str) -> Grid<N> {
-    let mut lines = input.lines().map(|s| s.as_bytes());
-    Grid(array::from_fn(|_| {
-        lines.next().unwrap().try_into().unwrap()
-    }))
+/// When a cell's roll gets removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wave {
+    /// there was never a roll here
+    Empty,
+    /// removed in this round, counting from 1
+    Round(usize),
+    /// never accessible, part of the stable core
+    Core,
 }
 
-fn part_1<const SIZE: usize>(input: &str) -> usize {
-    let grid: Grid<SIZE> = parse(input);
-    let mut count = 0;
-    for i in 0..SIZE {
-        for j in 0..SIZE {
-            if grid.0[i][j] == b'@' && grid.is_unblocked(i, j) {
-                count += 1
+/// The order rolls get peeled off the grid in, a k-core decomposition of the rolls
+///
+/// Every round removes all the rolls that are accessible once the previous round's rolls are gone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Waves {
+    pub cells: Grid<Wave>,
+    /// number of rolls removed in each round, the first round at index 0
+    pub per_round: Vec<usize>,
+}
+
+impl Waves {
+    pub fn rounds(&self) -> usize {
+        self.per_round.len()
+    }
+
+    /// Total number of rolls removed, the answer to part 2
+    pub fn removed(&self) -> usize {
+        self.per_round.iter().sum()
+    }
+
+    pub fn core(&self) -> usize {
+        self.cells.iter().filter(|&&w| w == Wave::Core).count()
+    }
+}
+
+fn waves(input: &str, rule: &AccessRule) -> Waves {
+    let grid = parse(input);
+    let mut cells = Grid::new(grid.width(), grid.height(), Wave::Empty);
+    for pos in grid.positions() {
+        if grid[pos] == b'@' {
+            cells[pos] = Wave::Core;
+        }
+    }
+
+    let (mut removal, mut frontier) = Removal::new(grid, rule);
+    let mut per_round = Vec::new();
+    let mut next = Vec::new();
+    while !frontier.is_empty() {
+        per_round.push(frontier.len());
+        for &pos in &frontier {
+            cells[pos] = Wave::Round(per_round.len());
+            removal.remove(pos, &mut next);
+        }
+        frontier.clear();
+        std::mem::swap(&mut frontier, &mut next);
+    }
+    Waves { cells, per_round }
+}
+
+pub mod bitboard {
+    use crate::grid::Grid;
+
+    /// The rolls of a grid packed one bit per cell, each row taking as many `u64`s as it needs with column `c` at bit
+    /// `c % 64` of word `c / 64`
+    ///
+    /// Only handles the default [`AccessRule`](super::AccessRule), but works out which rolls are accessible 64 at a
+    /// time by adding up the 8 shifted neighbour rows with bit-sliced adders.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BitGrid {
+        words: Vec<u64>,
+        words_per_row: usize,
+        width: usize,
+        height: usize,
+    }
+
+    /// Adds 3 bits in every lane, returning the (sum, carry) bits
+    #[inline(always)]
+    fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+        let ab = a ^ b;
+        (ab ^ c, (a & b) | (ab & c))
+    }
+
+    /// The row with every lane holding the cell to its left
+    #[inline(always)]
+    fn west(row: &[u64], w: usize) -> u64 {
+        let carry = if w > 0 { row[w - 1] >> 63 } else { 0 };
+        (row[w] << 1) | carry
+    }
+
+    /// The row with every lane holding the cell to its right
+    #[inline(always)]
+    fn east(row: &[u64], w: usize) -> u64 {
+        let carry = if w + 1 < row.len() {
+            row[w + 1] << 63
+        } else {
+            0
+        };
+        (row[w] >> 1) | carry
+    }
+
+    impl BitGrid {
+        pub fn from_grid(grid: &Grid<u8>) -> Self {
+            let words_per_row = grid.width().div_ceil(64);
+            let mut words = vec![0; words_per_row * grid.height()];
+            for (i, j) in grid.positions() {
+                if grid[(i, j)] == b'@' {
+                    words[i * words_per_row + j / 64] |= 1 << (j % 64);
+                }
+            }
+            Self {
+                words,
+                words_per_row,
+                width: grid.width(),
+                height: grid.height(),
             }
         }
+
+        pub fn to_grid(&self) -> Grid<u8> {
+            let mut grid = Grid::new(self.width, self.height, b'.');
+            for (i, j) in grid.positions() {
+                if self.row(i)[j / 64] >> (j % 64) & 1 == 1 {
+                    grid[(i, j)] = b'@';
+                }
+            }
+            grid
+        }
+
+        fn row(&self, i: usize) -> &[u64] {
+            &self.words[i * self.words_per_row..(i + 1) * self.words_per_row]
+        }
+
+        /// Number of rolls on the grid
+        pub fn count(&self) -> usize {
+            self.words.iter().map(|w| w.count_ones() as usize).sum()
+        }
+
+        /// Write a mask of the accessible rolls in row `i` into `out`
+        ///
+        /// A roll is accessible with fewer than 4 neighbours, so only the 4s bit of the neighbour count (which can
+        /// reach 8) is needed.
+        pub fn accessible_row(&self, i: usize, out: &mut [u64]) {
+            let empty = [0; 0];
+            let up = if i > 0 { self.row(i - 1) } else { &empty };
+            let mid = self.row(i);
+            let down = if i + 1 < self.height {
+                self.row(i + 1)
+            } else {
+                &empty
+            };
+            for (w, out) in out.iter_mut().enumerate() {
+                let (up_w, up, up_e) = if up.is_empty() {
+                    (0, 0, 0)
+                } else {
+                    (west(up, w), up[w], east(up, w))
+                };
+                let (down_w, down, down_e) = if down.is_empty() {
+                    (0, 0, 0)
+                } else {
+                    (west(down, w), down[w], east(down, w))
+                };
+                let (s1, c1) = full_add(up_w, up, up_e);
+                let (s2, c2) = full_add(down_w, down, down_e);
+                let (mid_w, mid_e) = (west(mid, w), east(mid, w));
+                let (s3, c3) = (mid_w ^ mid_e, mid_w & mid_e);
+                // ones and twos column, anything carried out of the twos is a 4
+                let (_, c4) = full_add(s1, s2, s3);
+                let (t, c5) = full_add(c1, c2, c3);
+                let c6 = t & c4;
+                *out = mid[w] & !(c5 | c6);
+            }
+        }
+
+        /// Number of rolls that are accessible
+        pub fn accessible(&self) -> usize {
+            let mut mask = vec![0; self.words_per_row];
+            (0..self.height)
+                .map(|i| {
+                    self.accessible_row(i, &mut mask);
+                    mask.iter().map(|w| w.count_ones() as usize).sum::<usize>()
+                })
+                .sum()
+        }
+
+        /// Remove every accessible roll row by row, returning how many were removed
+        ///
+        /// Rows are cleared as soon as they're worked out, so a row can already see what was removed above it.
+        pub fn remove_accessible(&mut self) -> usize {
+            let mut mask = vec![0; self.words_per_row];
+            let mut count = 0;
+            for i in 0..self.height {
+                self.accessible_row(i, &mut mask);
+                let start = i * self.words_per_row;
+                for (word, &m) in self.words[start..start + self.words_per_row]
+                    .iter_mut()
+                    .zip(&mask)
+                {
+                    *word &= !m;
+                    count += m.count_ones() as usize;
+                }
+            }
+            count
+        }
     }
-    count
 }
 
-fn part_2<const SIZE: usize>(input: &str) -> usize {
-    let mut grid: Grid<SIZE> = parse(input);
+/// Frames of the removal rounds, as text and as binary PPM images
+pub mod render {
+    use std::fs::{self, File};
+    use std::io::{self, BufWriter, Write};
+    use std::path::Path;
+
+    use super::{Wave, Waves};
+
+    const EMPTY: [u8; 3] = [0, 0, 0];
+    const STANDING: [u8; 3] = [128, 128, 128];
+
+    /// The grid as it stands during `round`, rolls removed in that round shown as `x`
+    pub fn ascii(waves: &Waves, round: usize) -> String {
+        let cells = &waves.cells;
+        let mut frame = String::with_capacity((cells.width() + 1) * cells.height());
+        for row in cells.rows() {
+            frame.extend(row.iter().map(|&cell| match cell {
+                Wave::Round(r) if r == round => 'x',
+                Wave::Round(r) if r < round => '.',
+                Wave::Round(_) | Wave::Core => '@',
+                Wave::Empty => '.',
+            }));
+            frame.push('\n');
+        }
+        frame
+    }
+
+    /// Colour of the rolls removed in `round`, running from blue for the first round to red for the last
+    fn colour(round: usize, rounds: usize) -> [u8; 3] {
+        let t = ((round - 1) * 255 / (rounds - 1).max(1)) as u8;
+        [t, 0, 255 - t]
+    }
+
+    /// The grid as it stands during `round` as a PPM image with a pixel per cell
+    ///
+    /// Rolls are coloured by the round they're removed in, dimmed once they're gone, and grey while they're still
+    /// standing.
+    pub fn ppm(waves: &Waves, round: usize, out: &mut impl Write) -> io::Result<()> {
+        let cells = &waves.cells;
+        write!(out, "P6\n{} {}\n255\n", cells.width(), cells.height())?;
+        for &cell in cells.iter() {
+            let pixel = match cell {
+                Wave::Round(r) if r == round => colour(r, waves.rounds()),
+                Wave::Round(r) if r < round => colour(r, waves.rounds()).map(|c| c / 3),
+                Wave::Round(_) | Wave::Core => STANDING,
+                Wave::Empty => EMPTY,
+            };
+            out.write_all(&pixel)?;
+        }
+        Ok(())
+    }
+
+    /// Write `frame_NNN.txt` and `frame_NNN.ppm` for every round to `dir`, returning the number of rounds
+    pub fn write_frames(waves: &Waves, dir: &Path) -> io::Result<usize> {
+        fs::create_dir_all(dir)?;
+        for round in 1..=waves.rounds() {
+            fs::write(
+                dir.join(format!("frame_{round:03}.txt")),
+                ascii(waves, round),
+            )?;
+            let mut out = BufWriter::new(File::create(dir.join(format!("frame_{round:03}.ppm")))?);
+            ppm(waves, round, &mut out)?;
+            out.flush()?;
+        }
+        Ok(waves.rounds())
+    }
+}
+
+fn part_1_bitboard(input: &str) -> usize {
+    BitGrid::from_grid(&parse(input)).accessible()
+}
+
+fn part_2_bitboard(input: &str) -> usize {
+    let mut grid = BitGrid::from_grid(&parse(input));
     let mut count = 0;
-    let mut res = remove_accessible(&mut grid);
+    let mut res = grid.remove_accessible();
     while res != 0 {
         count += res;
-        res = remove_accessible(&mut grid);
+        res = grid.remove_accessible();
     }
     count
 }
@@ -94,29 +549,308 @@ fn part_2<const SIZE: usize>(input: &str) -> usize {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::rng::Rng;
 
     const DATA: &str = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.\n";
 
-    const DATA_SIZE: usize = {
-        let bytes = DATA.as_bytes();
-        let mut len = 0;
-        while len < bytes.len() && bytes[len] != b'\n' {
-            len += 1
-        }
-        len
-    };
-
     #[test]
     fn part_1_example() {
-        let answer = part_1::<DATA_SIZE>(DATA);
+        let answer = part_1(DATA);
 
         assert_eq!(answer, 13)
     }
 
     #[test]
     fn part_2_example() {
-        let answer = part_2::<DATA_SIZE>(DATA);
+        let answer = part_2(DATA);
 
         assert_eq!(answer, 43)
     }
+
+    fn remove_accessible(grid: &mut Grid<u8>, rule: &AccessRule) -> usize {
+        let offsets = rule.neighbourhood.offsets();
+        let mut count = 0;
+        for (i, j) in grid.positions() {
+            if grid[(i, j)] == b'@' && rule.allows(count_rolls(grid, i, j, &offsets, rule.edges)) {
+                count += 1;
+                grid[(i, j)] = b'.';
+            }
+        }
+        count
+    }
+
+    /// the original part 2, sweeping the whole grid until nothing else can be removed
+    fn part_2_sweep(input: &str, rule: &AccessRule) -> usize {
+        let mut grid = parse(input);
+        let mut count = 0;
+        let mut res = remove_accessible(&mut grid, rule);
+        while res != 0 {
+            count += res;
+            res = remove_accessible(&mut grid, rule);
+        }
+        count
+    }
+
+    /// a `width` x `height` grid where roughly `density` percent of the cells are rolls
+    fn random_grid(width: usize, height: usize, density: u64, seed: u64) -> String {
+        let mut rng = Rng::new(seed);
+        let mut s = String::with_capacity((width + 1) * height);
+        for _ in 0..height {
+            for _ in 0..width {
+                s.push(if rng.below(100) < density { '@' } else { '.' });
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    #[test]
+    fn part_2_matches_sweep() {
+        let rule = AccessRule::default();
+        assert_eq!(part_2(DATA), part_2_sweep(DATA, &rule));
+        for (seed, density) in (1..20).zip((40..100).step_by(3)) {
+            let data = random_grid(31 + seed as usize, 17, density, seed);
+            assert_eq!(part_2(&data), part_2_sweep(&data, &rule), "{data}");
+        }
+    }
+
+    #[test]
+    fn waves_example() {
+        let waves = waves(DATA, &AccessRule::default());
+        assert_eq!(waves.per_round, [13, 12, 7, 5, 2, 1, 1, 1, 1]);
+        assert_eq!(waves.removed(), part_2(DATA));
+        assert_eq!(waves.core(), 71 - 43);
+        // the first round is everything accessible in part 1
+        let first = waves.cells.iter().filter(|&&w| w == Wave::Round(1)).count();
+        assert_eq!(first, part_1(DATA));
+        assert_eq!(waves.cells[(0, 0)], Wave::Empty);
+        assert_eq!(waves.cells[(0, 2)], Wave::Round(1));
+    }
+
+    #[test]
+    fn waves_are_rounds_of_the_sweep() {
+        // removing a round's rolls in one go, rather than as the sweep reaches them, gives the rounds
+        fn rounds(input: &str) -> Vec<usize> {
+            let rule = AccessRule::default();
+            let offsets = rule.neighbourhood.offsets();
+            let mut grid = parse(input);
+            let mut rounds = Vec::new();
+            loop {
+                let accessible: Vec<_> = grid
+                    .positions()
+                    .filter(|&(i, j)| {
+                        grid[(i, j)] == b'@'
+                            && rule.allows(count_rolls(&grid, i, j, &offsets, rule.edges))
+                    })
+                    .collect();
+                if accessible.is_empty() {
+                    break rounds;
+                }
+                rounds.push(accessible.len());
+                for pos in accessible {
+                    grid[pos] = b'.';
+                }
+            }
+        }
+        for seed in 1..10 {
+            let data = random_grid(40, 25, 75, seed);
+            assert_eq!(
+                waves(&data, &AccessRule::default()).per_round,
+                rounds(&data)
+            );
+        }
+    }
+
+    #[test]
+    fn render_ascii() {
+        let waves = waves("@@@\n@@@\n@@@\n", &AccessRule::default());
+        assert_eq!(waves.per_round, [4, 4, 1]);
+        assert_eq!(render::ascii(&waves, 1), "x@x\n@@@\nx@x\n");
+        assert_eq!(render::ascii(&waves, 2), ".x.\nx@x\n.x.\n");
+        assert_eq!(render::ascii(&waves, 3), "...\n.x.\n...\n");
+    }
+
+    #[test]
+    fn render_ppm() {
+        let waves = waves(DATA, &AccessRule::default());
+        let mut out = Vec::new();
+        render::ppm(&waves, 2, &mut out).unwrap();
+        let header = b"P6\n10 10\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(out.len(), header.len() + 3 * 100);
+        // (0, 0) is empty and (0, 2) went in the first round
+        let pixel = |i: usize, j: usize| &out[header.len() + 3 * (i * 10 + j)..][..3];
+        assert_eq!(pixel(0, 0), [0, 0, 0]);
+        assert_eq!(pixel(0, 2), [0, 0, 255 / 3]);
+    }
+
+    #[test]
+    fn render_frames() {
+        let dir = std::env::temp_dir().join(format!("aoc2025-day04-frames-{}", std::process::id()));
+        let waves = waves(DATA, &AccessRule::default());
+        assert_eq!(render::write_frames(&waves, &dir).unwrap(), 9);
+        let first = std::fs::read_to_string(dir.join("frame_001.txt")).unwrap();
+        assert_eq!(first.matches('x').count(), 13);
+        assert!(dir.join("frame_009.ppm").exists());
+        assert!(!dir.join("frame_010.txt").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bitboard_example() {
+        assert_eq!(part_1_bitboard(DATA), 13);
+        assert_eq!(part_2_bitboard(DATA), 43);
+    }
+
+    #[test]
+    fn bitboard_matches_bytes() {
+        let rule = AccessRule::default();
+        let offsets = rule.neighbourhood.offsets();
+        for (seed, width) in (1..).zip([1, 2, 63, 64, 65, 127, 128, 130, 200]) {
+            let data = random_grid(width, 9, 60, seed);
+            let grid = parse(&data);
+            let bits = BitGrid::from_grid(&grid);
+            assert_eq!(bits.to_grid(), grid);
+            assert_eq!(bits.count(), grid.iter().filter(|&&b| b == b'@').count());
+
+            let mut mask = vec![0; width.div_ceil(64)];
+            for i in 0..grid.height() {
+                bits.accessible_row(i, &mut mask);
+                for j in 0..width {
+                    let expected = grid[(i, j)] == b'@'
+                        && rule.allows(count_rolls(&grid, i, j, &offsets, rule.edges));
+                    assert_eq!(
+                        mask[j / 64] >> (j % 64) & 1 == 1,
+                        expected,
+                        "({i}, {j})\n{data}"
+                    );
+                }
+            }
+
+            assert_eq!(part_1_bitboard(&data), part_1(&data));
+            assert_eq!(part_2_bitboard(&data), part_2(&data));
+        }
+    }
+
+    #[test]
+    fn neighbourhood_offsets() {
+        assert_eq!(Neighbourhood::Moore(1).offsets().len(), 8);
+        assert_eq!(Neighbourhood::Moore(2).offsets().len(), 24);
+        assert_eq!(
+            Neighbourhood::VonNeumann(1).offsets(),
+            [(-1, 0), (0, -1), (0, 1), (1, 0)]
+        );
+        assert_eq!(Neighbourhood::VonNeumann(2).offsets().len(), 12);
+        assert_eq!(
+            Neighbourhood::Custom(vec![(0, 0), (0, 1)]).offsets(),
+            [(0, 1)]
+        );
+    }
+
+    #[test]
+    fn threshold_strictness() {
+        assert!(Threshold::Below(4).allows(3));
+        assert!(!Threshold::Below(4).allows(4));
+        assert!(Threshold::AtMost(4).allows(4));
+        assert!(!Threshold::AtMost(4).allows(5));
+    }
+
+    #[test]
+    fn rules_match_sweep() {
+        let rules = [
+            AccessRule {
+                neighbourhood: Neighbourhood::VonNeumann(1),
+                include_self: false,
+                threshold: Threshold::AtMost(2),
+                edges: Edges::Empty,
+            },
+            AccessRule {
+                neighbourhood: Neighbourhood::Moore(2),
+                include_self: true,
+                threshold: Threshold::Below(13),
+                edges: Edges::Empty,
+            },
+            AccessRule {
+                neighbourhood: Neighbourhood::VonNeumann(2),
+                include_self: false,
+                threshold: Threshold::Below(6),
+                edges: Edges::Empty,
+            },
+            // only looks right and down, so removals have to be pushed back up and left
+            AccessRule {
+                neighbourhood: Neighbourhood::Custom(vec![(0, 1), (1, 0), (1, 1), (0, 2)]),
+                include_self: false,
+                threshold: Threshold::AtMost(2),
+                edges: Edges::Empty,
+            },
+        ];
+        let edges = [Edges::Empty, Edges::Full, Edges::Wrap, Edges::Mirror];
+        let rules = rules.iter().flat_map(|rule| {
+            edges.map(|edges| AccessRule {
+                edges,
+                ..rule.clone()
+            })
+        });
+        for rule in &rules.collect::<Vec<_>>() {
+            assert_eq!(part_2_with(DATA, rule), part_2_sweep(DATA, rule));
+            for seed in 1..8 {
+                let data = random_grid(23, 19, 70, seed);
+                assert_eq!(part_2_with(&data, rule), part_2_sweep(&data, rule));
+            }
+        }
+    }
+
+    #[test]
+    fn von_neumann_example() {
+        let rule = AccessRule {
+            neighbourhood: Neighbourhood::VonNeumann(1),
+            include_self: false,
+            threshold: Threshold::Below(2),
+            edges: Edges::Empty,
+        };
+        // the ends of the line have 1 neighbour, the middle 2
+        assert_eq!(part_1_with("@@@\n", &rule), 2);
+        assert_eq!(part_2_with("@@@\n", &rule), 3);
+        // every roll of a 2x2 block has 2 neighbours
+        assert_eq!(part_2_with("@@\n@@\n", &rule), 0);
+    }
+
+    #[test]
+    fn edge_modes() {
+        let rule = |edges| AccessRule {
+            edges,
+            ..AccessRule::default()
+        };
+        // the corners of a full block see 3 rolls past each edge
+        assert_eq!(part_1_with("@@@\n@@@\n@@@\n", &rule(Edges::Empty)), 4);
+        assert_eq!(part_1_with("@@@\n@@@\n@@@\n", &rule(Edges::Full)), 0);
+        // on a torus nothing is ever at the edge
+        assert_eq!(part_2_with("@@@\n@@@\n@@@\n", &rule(Edges::Wrap)), 0);
+        assert_eq!(part_2_with("@@@\n@@@\n@@@\n", &rule(Edges::Mirror)), 0);
+        // a lone roll in the corner is mirrored into the 3 cells around it
+        assert_eq!(part_1_with("@..\n...\n...\n", &rule(Edges::Mirror)), 1);
+        // a block in the corner only keeps the roll furthest from the mirrors accessible, while wrapping puts empty
+        // cells on every side
+        let block = "@@..\n@@..\n....\n";
+        assert_eq!(part_1_with(block, &rule(Edges::Mirror)), 1);
+        assert_eq!(part_1_with(block, &rule(Edges::Wrap)), 4);
+        assert_eq!(part_1_with(block, &rule(Edges::Full)), 1);
+        assert_eq!(part_2(DATA), part_2_with(DATA, &rule(Edges::Empty)));
+    }
+
+    #[test]
+    fn rectangular_input() {
+        assert_eq!(part_1("@@@@@\n"), 5);
+        for data in ["@@\n@@\n@@\n", "@@@\n@@@\n"] {
+            // only the corners start out with fewer than 4 neighbours, the middle two follow once they're gone
+            assert_eq!(part_1(data), 4);
+            assert_eq!(part_2(data), 6);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Malformed input!")]
+    fn ragged_input() {
+        part_1("..@\n@@\n");
+    }
 }
@@ -1,3 +1,9 @@
+use std::fmt::Display;
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::ops::RangeInclusive;
+
+use interval::{Bound, IntervalSet};
+
 #[cfg(feature = "input")]
 const INPUT: &str = include_str!("../input/day05.txt");
 
@@ -7,81 +13,466 @@ pub fn solve_part_1() -> usize {
 }
 
 #[cfg(feature = "input")]
-pub fn solve_part_2() -> u64 {
+pub fn solve_part_2() -> u128 {
     part_2(INPUT)
 }
 
-fn part_1(input: &str) -> usize {
-    let (left, right) = input
-        .split_once("\n\n")
-        .expect(r#"Input is not ranges and integers seperated by \n\n"#);
-    let ranges: Vec<_> = left
+pub mod interval {
+    use std::fmt::{Debug, Display};
+    use std::ops::RangeInclusive;
+    use std::str::FromStr;
+
+    /// An integer type an [`IntervalSet`] can hold
+    pub trait Bound: Copy + Ord + Debug + Display + FromStr {
+        /// The next value up, `None` at the maximum
+        fn succ(self) -> Option<Self>;
+        /// The next value down, `None` at the minimum
+        fn pred(self) -> Option<Self>;
+        /// The value `n` above this one, `None` if that's past the maximum
+        fn nth(self, n: u128) -> Option<Self>;
+        /// Number of values in `lo..=hi`, saturating at `u128::MAX`
+        fn count(lo: Self, hi: Self) -> u128;
+    }
+
+    // signed types step up by their unsigned counterpart so `nth` can cross the whole range
+    macro_rules! impl_bound {
+        ($($t:ty => $add:ident($u:ty)),*) => {
+            $(
+            impl Bound for $t {
+                fn succ(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+                fn pred(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+                fn nth(self, n: u128) -> Option<Self> {
+                    self.$add(<$u>::try_from(n).ok()?)
+                }
+                fn count(lo: Self, hi: Self) -> u128 {
+                    (hi.abs_diff(lo) as u128).saturating_add(1)
+                }
+            }
+        )*
+        };
+    }
+
+    impl_bound!(
+        i64 => checked_add_unsigned(u64),
+        u64 => checked_add(u64),
+        i128 => checked_add_unsigned(u128),
+        u128 => checked_add(u128)
+    );
+
+    /// A set of integers stored as sorted, disjoint, inclusive intervals
+    ///
+    /// Overlapping and touching ranges are merged when the set is built, so membership is a binary search over the
+    /// interval starts. A running count of the values before each interval makes [`rank`](Self::rank) and
+    /// [`select`](Self::select) binary searches too.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub struct IntervalSet<T> {
+        /// `(start, end)` pairs, sorted with a gap of at least one value between each
+        intervals: Vec<(T, T)>,
+        /// number of values in `intervals[..i]`, saturating at `u128::MAX`, with one extra entry for the whole set
+        prefix: Vec<u128>,
+    }
+
+    impl<T: Bound> IntervalSet<T> {
+        pub fn new() -> Self {
+            Self::from_sorted(Vec::new())
+        }
+
+        /// Wrap intervals that are already sorted, disjoint and not touching
+        fn from_sorted(intervals: Vec<(T, T)>) -> Self {
+            let mut prefix = Vec::with_capacity(intervals.len() + 1);
+            prefix.push(0u128);
+            for &(start, end) in &intervals {
+                let before = *prefix.last().unwrap();
+                prefix.push(before.saturating_add(T::count(start, end)));
+            }
+            Self { intervals, prefix }
+        }
+
+        /// Sort and merge the ranges in O(n log n), empty ranges are ignored
+        pub fn from_ranges(ranges: impl IntoIterator<Item = RangeInclusive<T>>) -> Self {
+            let mut ranges: Vec<(T, T)> = ranges
+                .into_iter()
+                .map(RangeInclusive::into_inner)
+                .filter(|(start, end)| start <= end)
+                .collect();
+            ranges.sort_unstable();
+
+            let mut intervals: Vec<(T, T)> = Vec::with_capacity(ranges.len());
+            for (start, end) in ranges {
+                match intervals.last_mut() {
+                    // overlapping or touching the previous interval
+                    Some((_, last)) if last.succ().is_none_or(|next| start <= next) => {
+                        *last = end.max(*last)
+                    }
+                    _ => intervals.push((start, end)),
+                }
+            }
+            Self::from_sorted(intervals)
+        }
+
+        pub fn contains(&self, value: T) -> bool {
+            let idx = self.intervals.partition_point(|&(start, _)| start <= value);
+            idx > 0 && value <= self.intervals[idx - 1].1
+        }
+
+        /// Total number of values covered, saturating at `u128::MAX`
+        pub fn len(&self) -> u128 {
+            self.prefix[self.intervals.len()]
+        }
+
+        /// Number of values in the set that are at most `value`
+        pub fn rank(&self, value: T) -> u128 {
+            let idx = self.intervals.partition_point(|&(start, _)| start <= value);
+            if idx == 0 {
+                return 0;
+            }
+            let (start, end) = self.intervals[idx - 1];
+            self.prefix[idx - 1].saturating_add(T::count(start, end.min(value)))
+        }
+
+        /// The `k`th value in the set counting from 0, `None` if the set has `k` values or fewer
+        pub fn select(&self, k: u128) -> Option<T> {
+            // the interval holding it is the first whose values run past `k`
+            let idx = self.prefix[1..].partition_point(|&before| before <= k);
+            let &(start, _) = self.intervals.get(idx)?;
+            start.nth(k - self.prefix[idx])
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.intervals.is_empty()
+        }
+
+        /// The disjoint intervals in increasing order
+        pub fn iter(&self) -> impl ExactSizeIterator<Item = RangeInclusive<T>> + '_ {
+            self.intervals.iter().map(|&(start, end)| start..=end)
+        }
+
+        /// The smallest range covering the whole set
+        pub fn hull(&self) -> Option<RangeInclusive<T>> {
+            let (start, _) = self.intervals.first()?;
+            let (_, end) = self.intervals.last()?;
+            Some(*start..=*end)
+        }
+
+        pub fn union(&self, other: &Self) -> Self {
+            Self::from_ranges(self.iter().chain(other.iter()))
+        }
+
+        /// Walks both sets at once, always stepping past whichever interval ends first
+        pub fn intersection(&self, other: &Self) -> Self {
+            let mut intervals = Vec::new();
+            let (mut a, mut b) = (
+                self.intervals.iter().peekable(),
+                other.intervals.iter().peekable(),
+            );
+            while let (Some(&&(s1, e1)), Some(&&(s2, e2))) = (a.peek(), b.peek()) {
+                let (start, end) = (s1.max(s2), e1.min(e2));
+                if start <= end {
+                    intervals.push((start, end));
+                }
+                if e1 < e2 {
+                    a.next();
+                } else {
+                    b.next();
+                }
+            }
+            // pieces of the same interval are split by a gap in the other set, so they never touch
+            Self::from_sorted(intervals)
+        }
+
+        /// Everything in `bounds` that isn't in the set
+        pub fn complement(&self, bounds: RangeInclusive<T>) -> Self {
+            let (lo, hi) = bounds.into_inner();
+            let mut intervals = Vec::new();
+            if lo > hi {
+                return Self::new();
+            }
+            // start of the next gap, `None` once the set runs past the top of the bounds
+            let mut next = Some(lo);
+            for &(start, end) in &self.intervals {
+                let Some(gap) = next else { break };
+                if start > hi {
+                    break;
+                }
+                if end < gap {
+                    continue;
+                }
+                if start > gap {
+                    // `start > gap` so there's always a value before it
+                    intervals.push((gap, start.pred().unwrap()));
+                }
+                next = end.succ().filter(|&n| n <= hi);
+            }
+            if let Some(gap) = next {
+                intervals.push((gap, hi));
+            }
+            Self::from_sorted(intervals)
+        }
+
+        pub fn difference(&self, other: &Self) -> Self {
+            match self.hull() {
+                Some(hull) => self.intersection(&other.complement(hull)),
+                None => Self::new(),
+            }
+        }
+
+        pub fn symmetric_difference(&self, other: &Self) -> Self {
+            self.difference(other).union(&other.difference(self))
+        }
+
+        pub fn is_subset(&self, other: &Self) -> bool {
+            self.difference(other).is_empty()
+        }
+
+        pub fn overlaps(&self, other: &Self) -> bool {
+            let (mut a, mut b) = (
+                self.intervals.iter().peekable(),
+                other.intervals.iter().peekable(),
+            );
+            while let (Some(&&(s1, e1)), Some(&&(s2, e2))) = (a.peek(), b.peek()) {
+                if s1.max(s2) <= e1.min(e2) {
+                    return true;
+                }
+                if e1 < e2 {
+                    a.next();
+                } else {
+                    b.next();
+                }
+            }
+            false
+        }
+    }
+
+    impl<T: Bound> Default for IntervalSet<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: Bound> FromIterator<RangeInclusive<T>> for IntervalSet<T> {
+        fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+            Self::from_ranges(iter)
+        }
+    }
+}
+
+#[cfg(feature = "input")]
+pub fn solve_fresh() -> IntervalSet<u64> {
+    let (left, _) =
+        split_sections(INPUT).expect("Input is not ranges and integers seperated by a blank line");
+    parse_ranges(left)
+}
+
+/// Split the input at the first blank line, with either `\n` or `\r\n` line endings
+fn split_sections(input: &str) -> Option<(&str, &str)> {
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']).is_empty() && line.ends_with('\n') {
+            return Some((&input[..offset], &input[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// What to do with a range whose start is past its end, like `20-16`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Reversed {
+    /// fail to parse it
+    #[default]
+    Reject,
+    /// swap the bounds, `20-16` is `16-20`
+    Swap,
+    /// treat it as an empty range
+    Skip,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRangeError {
+    NotARange,
+    Left(String),
+    Right(String),
+    Reversed,
+}
+
+impl Display for ParseRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotARange => write!(f, "Invalid input! Not a '-' seperated range!"),
+            Self::Left(e) => write!(f, "Left side of range not an int: {e}"),
+            Self::Right(e) => write!(f, "Right side of range not an int: {e}"),
+            Self::Reversed => write!(f, "Range starts after it ends"),
+        }
+    }
+}
+
+impl std::error::Error for ParseRangeError {}
+
+/// Parse a `start-end` range, where either bound can be negative
+///
+/// The separator is the first `-` after the first character, so a leading `-` always belongs to the start and
+/// `-5--3` is `-5..=-3`.
+pub fn parse_range<T: Bound>(
+    range_str: &str,
+    reversed: Reversed,
+) -> Result<RangeInclusive<T>, ParseRangeError>
+where
+    T::Err: Display,
+{
+    let sep = range_str
+        .get(1..)
+        .and_then(|rest| rest.find('-'))
+        .ok_or(ParseRangeError::NotARange)?
+        + 1;
+    let (left_str, right_str) = (&range_str[..sep], &range_str[sep + 1..]);
+    let left: T = left_str
+        .parse()
+        .map_err(|e: T::Err| ParseRangeError::Left(e.to_string()))?;
+    let right: T = right_str
+        .parse()
+        .map_err(|e: T::Err| ParseRangeError::Right(e.to_string()))?;
+    match reversed {
+        _ if left <= right => Ok(left..=right),
+        Reversed::Reject => Err(ParseRangeError::Reversed),
+        Reversed::Swap => Ok(right..=left),
+        // already empty, `IntervalSet` drops it
+        Reversed::Skip => Ok(left..=right),
+    }
+}
+
+fn parse_ranges(section: &str) -> IntervalSet<u64> {
+    section
         .lines()
-        .map(|range_str| {
-            let (left_str, right_str) = range_str
-                .split_once("-")
-                .expect("Invalid input! Not a '-' seperated range!");
-            let left = left_str
-                .parse::<u64>()
-                .expect("Left side of range not an int");
-            let right = right_str
-                .parse::<u64>()
-                .expect("Right side of range not an int");
-            left..=right
-        })
-        .collect();
+        .map(|range_str| parse_range(range_str, Reversed::Reject).unwrap_or_else(|e| panic!("{e}")))
+        .collect()
+}
+
+fn part_1(input: &str) -> usize {
+    let (left, right) =
+        split_sections(input).expect("Input is not ranges and integers seperated by a blank line");
+    let ranges = parse_ranges(left);
     let mut count = 0;
     for s in right.lines() {
         let i: u64 = s
             .parse()
             .expect("Failed to parse integer in second section!");
-        if ranges.iter().any(|range| range.contains(&i)) {
+        if ranges.contains(i) {
             count += 1
         }
     }
     count
 }
 
-fn part_2(input: &str) -> u64 {
-    let (left, _) = input
-        .split_once("\n\n")
-        .expect(r#"Input is not ranges and integers seperated by \n\n"#);
-    let ranges = left.lines().map(|range_str| {
-        let (left_str, right_str) = range_str
-            .split_once("-")
-            .expect("Invalid input! Not a '-' seperated range!");
-        let left = left_str
-            .parse::<u64>()
-            .expect("Left side of range not an int");
-        let right = right_str
-            .parse::<u64>()
-            .expect("Right side of range not an int");
-        (left, right)
-    });
-
-    let mut encountered: Vec<(u64, u64)> = Vec::new();
-    for (left, right) in ranges {
-        // select ranges that aren't disjoint with ours
-        let merges: Vec<_> = encountered
-            .extract_if(.., |(l, r)| left <= *r && *l <= right)
-            .collect();
-        encountered.push(
-            merges
-                .into_iter()
-                .fold((left, right), |(l1, r1), (l2, r2)| (l1.min(l2), r1.max(r2))),
-        );
+/// Number of fresh IDs, which is a `u128` since every `u64` can be fresh
+fn part_2(input: &str) -> u128 {
+    let (left, _) =
+        split_sections(input).expect("Input is not ranges and integers seperated by a blank line");
+    parse_ranges(left).len()
+}
+
+/// What [`check_ids`] writes out for the IDs it reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Report {
+    /// a `<id> fresh` or `<id> spoiled` line per ID
+    Each,
+    /// nothing, only the returned [`Summary`]
+    Summary,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub fresh: u64,
+    pub spoiled: u64,
+}
+
+fn invalid_data(line: usize, error: impl Display) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("line {line}: {error}"))
+}
+
+/// Read ranges one per line up to the first blank line or the end of `reader`
+pub fn read_ranges<T: Bound>(
+    reader: &mut impl BufRead,
+    reversed: Reversed,
+) -> io::Result<IntervalSet<T>>
+where
+    T::Err: Display,
+{
+    let mut buf = String::new();
+    let mut ranges = Vec::new();
+    for line in 1.. {
+        buf.clear();
+        if reader.read_line(&mut buf)? == 0 {
+            break;
+        }
+        let range_str = buf.trim_end_matches(['\r', '\n']);
+        if range_str.is_empty() {
+            break;
+        }
+        ranges.push(parse_range(range_str, reversed).map_err(|e| invalid_data(line, e))?);
     }
+    Ok(IntervalSet::from_ranges(ranges))
+}
 
-    let mut sum = 0;
-    for (left, right) in encountered {
-        sum += 1 + right - left
+/// Check every ID in `reader`, one per line, against the fresh ranges without holding on to any of them
+///
+/// Line numbers in errors count from the start of `reader`.
+pub fn check_ids<T: Bound>(
+    fresh: &IntervalSet<T>,
+    mut reader: impl BufRead,
+    mut out: impl Write,
+    report: Report,
+) -> io::Result<Summary>
+where
+    T::Err: Display,
+{
+    let mut buf = String::new();
+    let mut summary = Summary::default();
+    for line in 1.. {
+        buf.clear();
+        if reader.read_line(&mut buf)? == 0 {
+            break;
+        }
+        let id_str = buf.trim_end_matches(['\r', '\n']);
+        if id_str.is_empty() {
+            continue;
+        }
+        let id: T = id_str.parse().map_err(|e| invalid_data(line, e))?;
+        let is_fresh = fresh.contains(id);
+        if is_fresh {
+            summary.fresh += 1
+        } else {
+            summary.spoiled += 1
+        }
+        if report == Report::Each {
+            writeln!(out, "{id} {}", if is_fresh { "fresh" } else { "spoiled" })?;
+        }
     }
-    sum
+    out.flush()?;
+    Ok(summary)
+}
+
+/// Build the range index from the first section of `reader`, then check the IDs that follow as they're read
+pub fn stream<T: Bound>(
+    mut reader: impl BufRead,
+    out: impl Write,
+    report: Report,
+    reversed: Reversed,
+) -> io::Result<Summary>
+where
+    T::Err: Display,
+{
+    let fresh: IntervalSet<T> = read_ranges(&mut reader, reversed)?;
+    check_ids(&fresh, reader, out, report)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::rng::Rng;
 
     const DATA: &str = "3-5\n10-14\n16-20\n12-18\n\n1\n5\n8\n11\n17\n32";
 
@@ -98,4 +489,286 @@ mod test {
 
         assert_eq!(answer, 14)
     }
+
+    #[test]
+    fn interval_set_merges() {
+        let set = IntervalSet::<u64>::from_ranges([
+            10..=14,
+            3..=5,
+            16..=20,
+            12..=18,
+            6..=6,
+            RangeInclusive::new(30, 29),
+        ]);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [3..=6, 10..=20]);
+        assert_eq!(set.len(), 15);
+        for (value, expected) in [
+            (2, false),
+            (3, true),
+            (6, true),
+            (7, false),
+            (9, false),
+            (10, true),
+            (20, true),
+            (21, false),
+        ] {
+            assert_eq!(set.contains(value), expected, "{value}");
+        }
+        assert!(IntervalSet::<u64>::new().is_empty());
+        assert!(!IntervalSet::<u64>::new().contains(0));
+    }
+
+    #[test]
+    fn interval_set_extremes() {
+        let set = IntervalSet::from_ranges([u64::MAX - 1..=u64::MAX, 0..=0, u64::MAX..=u64::MAX]);
+        assert_eq!(set.iter().len(), 2);
+        assert!(set.contains(u64::MAX));
+        assert_eq!(IntervalSet::from_ranges([0..=u64::MAX]).len(), 1 << 64);
+    }
+
+    /// the values `0..64` in an interval set as bits of a `u64`
+    fn to_bits(set: &IntervalSet<u64>) -> u64 {
+        set.iter()
+            .flatten()
+            .fold(0, |bits, value| bits | 1 << value)
+    }
+
+    /// a random set of up to 5 ranges within `0..64`
+    fn random_set(rng: &mut Rng) -> IntervalSet<u64> {
+        let n = rng.below(6);
+        (0..n)
+            .map(|_| {
+                let start = rng.below(64);
+                start..=(start + rng.below(12)).min(63)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn set_algebra_matches_bitset() {
+        let mut rng = Rng::new(0x2545f4914f6cdd1d);
+        for _ in 0..2000 {
+            let (a, b) = (random_set(&mut rng), random_set(&mut rng));
+            let (x, y) = (to_bits(&a), to_bits(&b));
+            assert_eq!(to_bits(&a.union(&b)), x | y, "{a:?} {b:?}");
+            assert_eq!(to_bits(&a.intersection(&b)), x & y, "{a:?} {b:?}");
+            assert_eq!(to_bits(&a.difference(&b)), x & !y, "{a:?} {b:?}");
+            assert_eq!(to_bits(&a.symmetric_difference(&b)), x ^ y, "{a:?} {b:?}");
+            assert_eq!(a.is_subset(&b), x & !y == 0, "{a:?} {b:?}");
+            assert_eq!(a.overlaps(&b), x & y != 0, "{a:?} {b:?}");
+
+            let (lo, hi) = (rng.below(64), rng.below(64));
+            let bounds = (lo..=hi).fold(0u64, |bits, value| bits | 1 << value);
+            assert_eq!(
+                to_bits(&a.complement(lo..=hi)),
+                bounds & !x,
+                "{a:?} {lo}..={hi}"
+            );
+
+            // everything comes back normalised
+            for set in [a.intersection(&b), a.difference(&b), a.complement(lo..=hi)] {
+                assert_eq!(set, IntervalSet::from_ranges(set.iter()));
+            }
+        }
+    }
+
+    #[test]
+    fn complement_extremes() {
+        let set = IntervalSet::from_ranges([0..=5, u64::MAX - 2..=u64::MAX]);
+        assert_eq!(
+            set.complement(0..=u64::MAX).iter().collect::<Vec<_>>(),
+            [6..=u64::MAX - 3]
+        );
+        assert_eq!(IntervalSet::new().complement(0..=u64::MAX).len(), 1 << 64);
+        assert!(set.complement(1..=4).is_empty());
+    }
+
+    #[test]
+    fn rank_select_example() {
+        let (left, _) = split_sections(DATA).unwrap();
+        let fresh = parse_ranges(left);
+        assert_eq!(fresh.rank(2), 0);
+        assert_eq!(fresh.rank(5), 3);
+        assert_eq!(fresh.rank(9), 3);
+        assert_eq!(fresh.rank(12), 6);
+        assert_eq!(fresh.rank(u64::MAX), 14);
+        assert_eq!(fresh.select(0), Some(3));
+        assert_eq!(fresh.select(3), Some(10));
+        assert_eq!(fresh.select(13), Some(20));
+        assert_eq!(fresh.select(14), None);
+    }
+
+    #[test]
+    fn rank_select_match_bitset() {
+        let mut rng = Rng::new(0x9e3779b97f4a7c15);
+        for _ in 0..500 {
+            let set = random_set(&mut rng);
+            let bits = to_bits(&set);
+            for value in 0..64 {
+                let below = if value == 63 {
+                    u64::MAX
+                } else {
+                    (1 << (value + 1)) - 1
+                };
+                assert_eq!(set.rank(value), (bits & below).count_ones() as u128);
+            }
+            let members: Vec<u64> = (0..64).filter(|v| bits >> v & 1 == 1).collect();
+            for k in 0..=members.len() {
+                assert_eq!(set.select(k as u128), members.get(k).copied());
+                if let Some(&value) = members.get(k) {
+                    assert_eq!(set.rank(value), k as u128 + 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn select_extremes() {
+        let set = IntervalSet::from_ranges([0..=0, u64::MAX - 1..=u64::MAX]);
+        assert_eq!(set.select(2), Some(u64::MAX));
+        assert_eq!(set.rank(u64::MAX), 3);
+        let full = IntervalSet::from_ranges([0..=u64::MAX]);
+        assert_eq!(full.select(u64::MAX as u128), Some(u64::MAX));
+        assert_eq!(full.select(1 << 64), None);
+    }
+
+    #[test]
+    fn crlf_example() {
+        let data = DATA.replace('\n', "\r\n");
+        assert_eq!(part_1(&data), 3);
+        assert_eq!(part_2(&data), 14);
+    }
+
+    #[test]
+    fn stream_each() {
+        let mut out = Vec::new();
+        let summary =
+            stream::<u64>(DATA.as_bytes(), &mut out, Report::Each, Reversed::Reject).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                fresh: 3,
+                spoiled: 3
+            }
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1 spoiled\n5 fresh\n8 spoiled\n11 fresh\n17 fresh\n32 spoiled\n"
+        );
+    }
+
+    #[test]
+    fn stream_summary_crlf() {
+        let data = DATA.replace('\n', "\r\n") + "\r\n";
+        let mut out = Vec::new();
+        let summary =
+            stream::<u64>(data.as_bytes(), &mut out, Report::Summary, Reversed::Reject).unwrap();
+        assert_eq!(summary.fresh, part_1(DATA) as u64);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn stream_reports_bad_lines() {
+        let data = "3-5\n1-x\n\n4\n".as_bytes();
+        let err = stream::<u64>(data, io::sink(), Report::Each, Reversed::Reject).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "line 2: Right side of range not an int: invalid digit found in string"
+        );
+
+        let fresh = IntervalSet::<u64>::from_ranges([3..=5]);
+        let err = check_ids(&fresh, "4\nfour\n".as_bytes(), io::sink(), Report::Each).unwrap_err();
+        assert!(err.to_string().starts_with("line 2: "));
+    }
+
+    #[test]
+    fn parse_negative_ranges() {
+        let parse = |s| parse_range::<i64>(s, Reversed::Reject);
+        assert_eq!(parse("-5--3"), Ok(-5..=-3));
+        assert_eq!(parse("-5-3"), Ok(-5..=3));
+        assert_eq!(parse("5-7"), Ok(5..=7));
+        assert_eq!(parse("-5"), Err(ParseRangeError::NotARange));
+        assert_eq!(parse("5"), Err(ParseRangeError::NotARange));
+        assert!(matches!(parse("--5-3"), Err(ParseRangeError::Left(_))));
+        assert!(matches!(parse("5--"), Err(ParseRangeError::Right(_))));
+        assert!(matches!(
+            parse_range::<u64>("-5-3", Reversed::Reject),
+            Err(ParseRangeError::Left(_))
+        ));
+    }
+
+    #[test]
+    fn parse_wide_ranges() {
+        let range = parse_range::<u128>(
+            "18446744073709551616-340282366920938463463374607431768211455",
+            Reversed::Reject,
+        );
+        assert_eq!(range, Ok(1 << 64..=u128::MAX));
+        let set = IntervalSet::from_ranges([range.unwrap()]);
+        assert!(set.contains(u128::MAX));
+        assert!(!set.contains(u64::MAX as u128));
+        assert_eq!(set.len(), u128::MAX - (1 << 64) + 1);
+        // the whole range is one more than fits
+        assert_eq!(IntervalSet::from_ranges([0..=u128::MAX]).len(), u128::MAX);
+    }
+
+    #[test]
+    fn reversed_policy() {
+        assert_eq!(
+            parse_range::<u64>("20-16", Reversed::Reject),
+            Err(ParseRangeError::Reversed)
+        );
+        assert_eq!(parse_range::<u64>("20-16", Reversed::Swap), Ok(16..=20));
+        let skipped = parse_range::<u64>("20-16", Reversed::Skip).unwrap();
+        assert!(IntervalSet::from_ranges([skipped]).is_empty());
+        assert_eq!(parse_range::<i64>("-3--5", Reversed::Swap), Ok(-5..=-3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Range starts after it ends")]
+    fn part_2_rejects_reversed() {
+        part_2("3-5\n20-16\n\n1\n");
+    }
+
+    #[test]
+    fn part_2_every_id() {
+        assert_eq!(part_2("0-18446744073709551615\n\n1\n"), 1 << 64);
+        assert_eq!(part_2("5-18446744073709551615\n0-4\n\n"), 1 << 64);
+    }
+
+    #[test]
+    fn signed_sets() {
+        let set = IntervalSet::<i64>::from_ranges([-10..=-5, -4..=2, 8..=9]);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [-10..=2, 8..=9]);
+        assert_eq!(set.len(), 15);
+        assert_eq!(set.rank(-1), 10);
+        assert_eq!(set.select(13), Some(8));
+        assert_eq!(
+            set.complement(-12..=10).iter().collect::<Vec<_>>(),
+            [-12..=-11, 3..=7, 10..=10]
+        );
+        let full = IntervalSet::from_ranges([i64::MIN..=i64::MAX]);
+        assert_eq!(full.len(), 1 << 64);
+        assert_eq!(full.select(u64::MAX as u128), Some(i64::MAX));
+        assert_eq!(full.rank(-1), 1 << 63);
+    }
+
+    #[test]
+    fn stream_signed() {
+        let data = "-5--3\n10-12\n\n-4\n0\n11\n-6\n";
+        let summary = stream::<i64>(
+            data.as_bytes(),
+            io::sink(),
+            Report::Summary,
+            Reversed::Reject,
+        );
+        assert_eq!(
+            summary.unwrap(),
+            Summary {
+                fresh: 2,
+                spoiled: 2
+            }
+        );
+    }
 }
@@ -1,10 +1,13 @@
 #[cfg(feature = "input")]
 const INPUT: &str = include_str!("../input/day08.txt");
 
-use std::collections::BTreeSet;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
-use vec3::Vec3;
+use kd_tree::KdTree;
+use metric::{Metric, SqEuclidean};
+use point::{Coord, Point, TryFromStrError, Vec3};
+use union_find::UnionFind;
 
 #[cfg(feature = "input")]
 pub fn solve_part_1() -> i32 {
@@ -12,175 +15,1078 @@ pub fn solve_part_1() -> i32 {
 }
 
 #[cfg(feature = "input")]
-pub fn solve_part_2() -> u64 {
-    part_2(INPUT, 1000)
+pub fn solve_part_2() -> Result<u64, JoinError> {
+    part_2(INPUT)
 }
 
-mod vec3 {
+#[cfg(feature = "input")]
+pub fn solve_linkage() -> Result<Linkage, DistanceOverflow> {
+    Linkage::new(&points(INPUT), SqEuclidean)
+}
+
+pub mod point {
+    use std::fmt::{Debug, Display};
+    use std::hash::Hash;
     use std::num::ParseIntError;
+    use std::ops::Index;
     use std::str::FromStr;
 
+    /// An integer coordinate
+    pub trait Coord:
+        Copy + Default + Ord + Hash + Debug + Display + FromStr<Err = ParseIntError>
+    {
+        /// Distance between two coordinates on the same axis
+        fn abs_diff(self, other: Self) -> u64;
+
+        /// The coordinate as an `i128`, which every coordinate type fits in
+        fn widen(self) -> i128;
+    }
+
+    macro_rules! coord {
+        ($($t:ty),*) => {
+            $(impl Coord for $t {
+                fn abs_diff(self, other: Self) -> u64 {
+                    u64::from(<$t>::abs_diff(self, other))
+                }
+
+                fn widen(self) -> i128 {
+                    i128::from(self)
+                }
+            })*
+        };
+    }
+
+    coord!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+    /// A point in `D` dimensions
     #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-    pub struct Vec3(u64, u64, u64);
+    pub struct Point<const D: usize, T>([T; D]);
 
-    impl Vec3 {
-        pub fn x(&self) -> u64 {
+    /// The junction boxes in the puzzle
+    pub type Vec3 = Point<3, u64>;
+
+    impl<const D: usize, T: Coord> Point<D, T> {
+        pub fn new(coords: [T; D]) -> Self {
+            Self(coords)
+        }
+        pub fn coords(&self) -> &[T; D] {
+            &self.0
+        }
+        /// Squared euclidean distance, widened so it fits for any three dimensional points whose coordinates are less
+        /// than 2⁶³ apart, `None` if it overflows
+        pub fn sq_dist(&self, other: &Self) -> Option<u128> {
             self.0
+                .iter()
+                .zip(&other.0)
+                .try_fold(0u128, |sum, (&a, &b)| {
+                    sum.checked_add((a.abs_diff(b) as u128).pow(2))
+                })
+        }
+    }
+
+    impl<T: Coord> Point<2, T> {
+        pub fn x(&self) -> T {
+            self.0[0]
         }
-        pub fn y(&self) -> u64 {
-            self.1
+        pub fn y(&self) -> T {
+            self.0[1]
         }
-        pub fn z(&self) -> u64 {
-            self.2
+    }
+
+    impl<T: Coord> Point<3, T> {
+        pub fn x(&self) -> T {
+            self.0[0]
+        }
+        pub fn y(&self) -> T {
+            self.0[1]
         }
-        pub fn sq_dist(&self, other: &Vec3) -> u64 {
-            (self.x().abs_diff(other.x())).pow(2)
-                + (self.y().abs_diff(other.y())).pow(2)
-                + (self.z().abs_diff(other.z())).pow(2)
+        pub fn z(&self) -> T {
+            self.0[2]
         }
     }
 
-    impl FromStr for Vec3 {
+    impl<const D: usize, T> Index<usize> for Point<D, T> {
+        type Output = T;
+
+        fn index(&self, axis: usize) -> &T {
+            &self.0[axis]
+        }
+    }
+
+    /// Comma separated, the same as it's parsed
+    impl<const D: usize, T: Coord> Display for Point<D, T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            for (axis, coord) in self.0.iter().enumerate() {
+                if axis > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{coord}")?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<const D: usize, T: Coord> FromStr for Point<D, T> {
         type Err = TryFromStrError;
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let mut split = s.split(',');
-            match (split.next(), split.next(), split.next(), split.next()) {
-                (None, _, _, _) | (_, None, _, _) | (_, _, None, _) | (_, _, _, Some(_)) => {
-                    Err(TryFromStrError::Not3CommaSeperatedValues)
-                }
-                (Some(x), Some(y), Some(z), _) => Ok(Vec3(x.parse()?, y.parse()?, z.parse()?)),
+            let found = s.split(',').count();
+            if found != D {
+                return Err(TryFromStrError::WrongDimension { expected: D, found });
+            }
+            let mut coords = [T::default(); D];
+            for (coord, value) in coords.iter_mut().zip(s.split(',')) {
+                *coord = value.parse()?;
             }
+            Ok(Point(coords))
         }
     }
 
-    #[derive(Debug)]
-    #[allow(dead_code)]
+    #[derive(Debug, Clone, PartialEq, Eq)]
     pub enum TryFromStrError {
-        Not3CommaSeperatedValues,
-        Not3Ints(ParseIntError),
+        WrongDimension { expected: usize, found: usize },
+        NotInts(ParseIntError),
+    }
+
+    impl Display for TryFromStrError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::WrongDimension { expected, found } => write!(
+                    f,
+                    "Expected {expected} comma seperated values, found {found}"
+                ),
+                Self::NotInts(e) => write!(f, "Coordinate not an int: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for TryFromStrError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Self::WrongDimension { .. } => None,
+                Self::NotInts(e) => Some(e),
+            }
+        }
     }
 
     impl From<ParseIntError> for TryFromStrError {
         fn from(value: ParseIntError) -> Self {
-            Self::Not3Ints(value)
+            Self::NotInts(value)
         }
     }
 }
 
-#[derive(Debug)]
-struct VecPair(Vec3, Vec3);
+pub mod metric {
+    use super::point::{Coord, Point};
+
+    /// How far apart two points are
+    ///
+    /// Only the order of distances matters for clustering, so a metric is free to hand back anything that grows with
+    /// the real distance, like the squared euclidean distance. It must never shrink as two points get further apart on
+    /// any one axis.
+    pub trait Metric {
+        /// `None` if the distance doesn't fit in a `u128`
+        fn dist<const D: usize, T: Coord>(&self, a: &Point<D, T>, b: &Point<D, T>) -> Option<u128>;
+
+        /// The least distance two points can be apart when they differ by `diff` on one axis
+        fn axis_dist(&self, diff: u64) -> u128;
+
+        /// What the distances are called in exported graphs
+        fn name(&self) -> &'static str;
+    }
+
+    /// Squared straight line distance
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct SqEuclidean;
+
+    /// Sum of the distances along each axis
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Manhattan;
+
+    /// Largest distance along any one axis
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Chebyshev;
+
+    impl Metric for SqEuclidean {
+        fn dist<const D: usize, T: Coord>(&self, a: &Point<D, T>, b: &Point<D, T>) -> Option<u128> {
+            a.sq_dist(b)
+        }
+
+        fn axis_dist(&self, diff: u64) -> u128 {
+            (diff as u128).pow(2)
+        }
+
+        fn name(&self) -> &'static str {
+            "sq_dist"
+        }
+    }
+
+    impl Metric for Manhattan {
+        fn dist<const D: usize, T: Coord>(&self, a: &Point<D, T>, b: &Point<D, T>) -> Option<u128> {
+            a.coords()
+                .iter()
+                .zip(b.coords())
+                .try_fold(0u128, |sum, (&a, &b)| {
+                    sum.checked_add(a.abs_diff(b) as u128)
+                })
+        }
+
+        fn axis_dist(&self, diff: u64) -> u128 {
+            diff as u128
+        }
+
+        fn name(&self) -> &'static str {
+            "manhattan_dist"
+        }
+    }
+
+    impl Metric for Chebyshev {
+        fn dist<const D: usize, T: Coord>(&self, a: &Point<D, T>, b: &Point<D, T>) -> Option<u128> {
+            a.coords()
+                .iter()
+                .zip(b.coords())
+                .map(|(&a, &b)| a.abs_diff(b) as u128)
+                .max()
+                .or(Some(0))
+        }
+
+        fn axis_dist(&self, diff: u64) -> u128 {
+            diff as u128
+        }
+
+        fn name(&self) -> &'static str {
+            "chebyshev_dist"
+        }
+    }
+}
+
+mod union_find {
+    /// Disjoint sets over `0..n` with path compression and union by size
+    #[derive(Debug, Clone)]
+    pub struct UnionFind {
+        parent: Vec<usize>,
+        size: Vec<usize>,
+    }
+
+    impl UnionFind {
+        pub fn new(n: usize) -> Self {
+            Self {
+                parent: (0..n).collect(),
+                size: vec![1; n],
+            }
+        }
+
+        /// The representative of the set holding `x`
+        pub fn find(&mut self, x: usize) -> usize {
+            let mut root = x;
+            while self.parent[root] != root {
+                root = self.parent[root];
+            }
+            // point everything on the way straight at the root
+            let mut x = x;
+            while self.parent[x] != root {
+                x = std::mem::replace(&mut self.parent[x], root);
+            }
+            root
+        }
+
+        /// Merge the sets holding `a` and `b`, returning whether they were separate
+        pub fn union(&mut self, a: usize, b: usize) -> bool {
+            let (mut a, mut b) = (self.find(a), self.find(b));
+            if a == b {
+                return false;
+            }
+            if self.size[a] < self.size[b] {
+                std::mem::swap(&mut a, &mut b);
+            }
+            self.parent[b] = a;
+            self.size[a] += self.size[b];
+            true
+        }
+
+        /// Number of elements across every set
+        pub fn len(&self) -> usize {
+            self.parent.len()
+        }
+
+        /// Size of the set holding `x`
+        pub fn size_of(&mut self, x: usize) -> usize {
+            let root = self.find(x);
+            self.size[root]
+        }
+
+        /// Size of every set, in no particular order
+        pub fn sizes(&self) -> impl Iterator<Item = usize> + '_ {
+            (0..self.parent.len())
+                .filter(|&x| self.parent[x] == x)
+                .map(|root| self.size[root])
+        }
+    }
+}
+
+/// Two points by their index, `left < right`
+///
+/// Ordered by distance first and then by the indices, so pairs at the same distance are all kept and always
+/// come out in the same order.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct VecPair {
+    dist: u128,
+    left: usize,
+    right: usize,
+}
 
 impl VecPair {
-    fn to_pair(&self) -> (Vec3, Vec3) {
-        (self.0, self.1)
+    fn to_pair(&self) -> (usize, usize) {
+        (self.left, self.right)
+    }
+}
+
+/// A distance that might not fit in a `u128`, which is still further than any that does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Dist {
+    Finite(u128),
+    Overflow,
+}
+
+impl From<Option<u128>> for Dist {
+    fn from(value: Option<u128>) -> Self {
+        value.map_or(Dist::Overflow, Dist::Finite)
+    }
+}
+
+mod kd_tree {
+    use std::collections::BinaryHeap;
+
+    use super::Dist;
+    use super::metric::Metric;
+    use super::point::{Coord, Point};
+
+    /// A k-d tree over point indices, stored implicitly with each node in the middle of its subtree's slice
+    #[derive(Debug)]
+    pub struct KdTree<'a, const D: usize, T, M> {
+        points: &'a [Point<D, T>],
+        nodes: Vec<usize>,
+        metric: M,
+    }
+
+    impl<'a, const D: usize, T: Coord, M: Metric> KdTree<'a, D, T, M> {
+        pub fn new(points: &'a [Point<D, T>], metric: M) -> Self {
+            let mut nodes: Vec<usize> = (0..points.len()).collect();
+            build(points, &mut nodes, 0);
+            Self {
+                points,
+                nodes,
+                metric,
+            }
+        }
+
+        /// The `k` points passing `filter` closest to `points[target]`, as `(dist, index)` nearest first with ties
+        /// broken by index
+        pub fn nearest(
+            &self,
+            target: usize,
+            k: usize,
+            filter: impl Fn(usize) -> bool,
+        ) -> Vec<(Dist, usize)> {
+            // max heap, so the worst of the best so far is on top
+            let mut best = BinaryHeap::with_capacity(k + 1);
+            if k > 0 {
+                self.search(&self.points[target], &self.nodes, 0, k, &filter, &mut best);
+            }
+            best.into_sorted_vec()
+        }
+
+        fn search(
+            &self,
+            target: &Point<D, T>,
+            nodes: &[usize],
+            depth: usize,
+            k: usize,
+            filter: &impl Fn(usize) -> bool,
+            best: &mut BinaryHeap<(Dist, usize)>,
+        ) {
+            if nodes.is_empty() {
+                return;
+            }
+            let mid = nodes.len() / 2;
+            let idx = nodes[mid];
+            let point = &self.points[idx];
+            if filter(idx) {
+                let candidate = (Dist::from(self.metric.dist(target, point)), idx);
+                if best.len() < k {
+                    best.push(candidate);
+                } else if candidate < *best.peek().unwrap() {
+                    best.pop();
+                    best.push(candidate);
+                }
+            }
+
+            let axis = depth % D;
+            let (t, p) = (target[axis], point[axis]);
+            let (near, far) = if t < p {
+                (&nodes[..mid], &nodes[mid + 1..])
+            } else {
+                (&nodes[mid + 1..], &nodes[..mid])
+            };
+            self.search(target, near, depth + 1, k, filter, best);
+            // nothing on the far side is closer than the splitting plane, ties still have to be looked at
+            let plane = Dist::Finite(self.metric.axis_dist(t.abs_diff(p)));
+            if best.len() < k || plane <= best.peek().unwrap().0 {
+                self.search(target, far, depth + 1, k, filter, best);
+            }
+        }
     }
-    fn sq_dist(&self) -> u64 {
-        self.0.sq_dist(&self.1)
+
+    fn build<const D: usize, T: Coord>(points: &[Point<D, T>], nodes: &mut [usize], depth: usize) {
+        if nodes.len() <= 1 {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        let axis = depth % D;
+        nodes.select_nth_unstable_by_key(mid, |&i| points[i][axis]);
+        let (left, right) = nodes.split_at_mut(mid);
+        build(points, left, depth + 1);
+        build(points, &mut right[1..], depth + 1);
     }
 }
 
-// order by square distance
-impl Ord for VecPair {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.sq_dist().cmp(&other.sq_dist())
+/// Neighbours fetched per point to start with, doubling every time a point runs out
+const BATCH: usize = 8;
+
+/// The neighbours of one point with a higher index, fetched from the tree a batch at a time
+#[derive(Debug, Default)]
+struct Cursor {
+    batch: Vec<(Dist, usize)>,
+    next: usize,
+    /// size of the last batch asked for
+    asked: usize,
+}
+
+/// Every pair of points in the same order as [`VecPair`], worked out lazily
+///
+/// Each point streams its neighbours with a higher index nearest first out of a k-d tree, and a heap merges the head
+/// of every stream. Only the pairs that are actually used get found, rather than all n² of them up front.
+///
+/// A pair too far apart to measure is still further than any that can be, so everything up to the first of those comes
+/// out in order and then it's an error.
+#[derive(Debug)]
+struct ClosestPairs<'a, const D: usize, T, M> {
+    tree: KdTree<'a, D, T, M>,
+    cursors: Vec<Cursor>,
+    /// `(dist, left, right)` at the head of each stream
+    heap: BinaryHeap<Reverse<(Dist, usize, usize)>>,
+}
+
+/// Two points too far apart for their distance to fit in a `u128`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DistanceOverflow;
+
+impl std::fmt::Display for DistanceOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Points too far apart for their distance to fit in a u128"
+        )
     }
 }
 
-impl PartialOrd for VecPair {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl std::error::Error for DistanceOverflow {}
+
+impl<'a, const D: usize, T: Coord, M: Metric> ClosestPairs<'a, D, T, M> {
+    fn new(points: &'a [Point<D, T>], metric: M) -> Self {
+        let mut pairs = Self {
+            tree: KdTree::new(points, metric),
+            cursors: (0..points.len()).map(|_| Cursor::default()).collect(),
+            heap: BinaryHeap::with_capacity(points.len()),
+        };
+        for left in 0..points.len() {
+            if let Some(pair) = pairs.advance(left) {
+                pairs.heap.push(Reverse(pair));
+            }
+        }
+        pairs
+    }
+
+    /// The next pair in `left`'s stream
+    fn advance(&mut self, left: usize) -> Option<(Dist, usize, usize)> {
+        let cursor = &mut self.cursors[left];
+        if cursor.next == cursor.batch.len() {
+            // a short batch means there was nothing more to find
+            if cursor.batch.len() < cursor.asked {
+                return None;
+            }
+            cursor.asked = (cursor.asked * 2).max(BATCH);
+            // the order is total, so the bigger batch starts with everything already handed out
+            cursor.batch = self.tree.nearest(left, cursor.asked, |right| right > left);
+        }
+        let &(dist, right) = cursor.batch.get(cursor.next)?;
+        cursor.next += 1;
+        Some((dist, left, right))
     }
 }
 
-// compare equality order agnostic to match distance based Ord and ignore duplicates
-impl PartialEq for VecPair {
-    fn eq(&self, other: &Self) -> bool {
-        (self.0 == other.0 && self.1 == other.1) || (self.0 == other.1 && self.1 == other.0)
+impl<const D: usize, T: Coord, M: Metric> Iterator for ClosestPairs<'_, D, T, M> {
+    type Item = Result<VecPair, DistanceOverflow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((dist, left, right)) = self.heap.pop()?;
+        if let Some(next) = self.advance(left) {
+            self.heap.push(Reverse(next));
+        }
+        Some(match dist {
+            Dist::Finite(dist) => Ok(VecPair { dist, left, right }),
+            Dist::Overflow => Err(DistanceOverflow),
+        })
     }
 }
 
-impl Eq for VecPair {}
+/// One step of the single-linkage hierarchy, joining two circuits with an edge of the minimum spanning tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Merge {
+    /// The points the edge joins, `left < right`
+    pub left: usize,
+    pub right: usize,
+    pub dist: u128,
+    /// Connections made up to and including this one, counting those within a circuit that didn't join anything
+    pub connections: usize,
+    /// The circuits joined, numbered like a dendrogram: point `i` starts out as circuit `i` and merge `k` makes
+    /// circuit `n + k`
+    pub circuits: (usize, usize),
+    /// Size of the circuit the merge makes
+    pub size: usize,
+}
 
-#[derive(Debug, PartialEq, Eq)]
-struct Circuit {
-    nodes: HashSet<Vec3>,
+/// Every merge that connecting the closest pairs one at a time ever makes, in order
+///
+/// This is Kruskal's algorithm run to the end, so the merges are the edges of the minimum spanning tree, and any
+/// prefix of them is where the circuits stand after some number of connections or up to some distance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Linkage {
+    len: usize,
+    merges: Vec<Merge>,
+    /// [`Metric::name`] of the metric it was built with
+    metric: &'static str,
 }
 
-// order by size of each circuit, starting with the largest
-impl Ord for Circuit {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.nodes.len().cmp(&other.nodes.len()).reverse()
+impl Linkage {
+    /// The hierarchy of `points` by `metric`, failing if two circuits can only be joined by a pair too far apart to
+    /// measure
+    pub fn new<const D: usize, T: Coord>(
+        points: &[Point<D, T>],
+        metric: impl Metric,
+    ) -> Result<Self, DistanceOverflow> {
+        let len = points.len();
+        let name = metric.name();
+        let mut circuits = UnionFind::new(len);
+        // the dendrogram id of the circuit each root stands for
+        let mut ids: Vec<usize> = (0..len).collect();
+        let mut merges = Vec::with_capacity(len.saturating_sub(1));
+        for (i, pair) in ClosestPairs::new(points, metric).enumerate() {
+            if merges.len() + 1 >= len {
+                break;
+            }
+            let pair = pair?;
+            let (left, right) = pair.to_pair();
+            let joined = (ids[circuits.find(left)], ids[circuits.find(right)]);
+            if circuits.union(left, right) {
+                ids[circuits.find(left)] = len + merges.len();
+                merges.push(Merge {
+                    left,
+                    right,
+                    dist: pair.dist,
+                    connections: i + 1,
+                    circuits: joined,
+                    size: circuits.size_of(left),
+                });
+            }
+        }
+        Ok(Self {
+            len,
+            merges,
+            metric: name,
+        })
+    }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn merges(&self) -> &[Merge] {
+        &self.merges
+    }
+
+    /// What the distances in the merges are, see [`Metric::name`]
+    pub fn metric(&self) -> &'static str {
+        self.metric
+    }
+
+    /// The minimum spanning tree as `(left, right, dist)` edges, shortest first
+    pub fn mst(&self) -> impl Iterator<Item = (usize, usize, u128)> + '_ {
+        self.merges.iter().map(|m| (m.left, m.right, m.dist))
+    }
+
+    /// The merges made by the first `connections` connections
+    pub fn cut_connections(&self, connections: usize) -> &[Merge] {
+        let end = self
+            .merges
+            .partition_point(|m| m.connections <= connections);
+        &self.merges[..end]
+    }
+
+    /// The merges made by connecting every pair at most `dist` apart
+    pub fn cut_distance(&self, dist: u128) -> &[Merge] {
+        let end = self.merges.partition_point(|m| m.dist <= dist);
+        &self.merges[..end]
+    }
+
+    /// The merges made up to `cut`
+    pub fn cut(&self, cut: Cut) -> &[Merge] {
+        match cut {
+            Cut::Connections(connections) => self.cut_connections(connections),
+            Cut::Distance(dist) => self.cut_distance(dist),
+        }
+    }
+
+    /// The circuits after cutting the hierarchy at `cut`, without going back over the pairs
+    pub fn circuits(&self, cut: Cut) -> Circuits {
+        let mut sets = UnionFind::new(self.len);
+        for m in self.cut(cut) {
+            sets.union(m.left, m.right);
+        }
+        Circuits::new(&mut sets)
     }
 }
 
-impl PartialOrd for Circuit {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl Circuits {
+    fn new(sets: &mut UnionFind) -> Self {
+        let len = sets.len();
+        let mut roots = vec![usize::MAX; len];
+        let mut members: Vec<Vec<usize>> = Vec::new();
+        for point in 0..len {
+            let root = sets.find(point);
+            if roots[root] == usize::MAX {
+                roots[root] = members.len();
+                members.push(Vec::with_capacity(sets.size_of(root)));
+            }
+            members[roots[root]].push(point);
+        }
+        // largest first, and then by their lowest point as they were found in that order
+        members.sort_by_key(|m| Reverse(m.len()));
+
+        let mut circuit_of = vec![0; len];
+        for (circuit, points) in members.iter().enumerate() {
+            for &point in points {
+                circuit_of[point] = circuit;
+            }
+        }
+        Self {
+            members,
+            circuit_of,
+        }
     }
 }
 
-fn parse(input: &str) -> BTreeSet<VecPair> {
-    let lines = input.lines();
-    // a set of each point paired with every other point, brute force and slow, but not that bad given our n
-    lines
-        .clone()
-        .flat_map(|l| {
-            lines.clone().filter(move |&r| l != r).map(|r| {
-                let left = l.parse::<Vec3>().expect("Malformed input!");
-                let right = r.parse::<Vec3>().expect("Malformed input!");
-                VecPair(left, right)
+/// Where to cut the hierarchy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cut {
+    /// After this many of the closest pairs have been connected
+    Connections(usize),
+    /// After every pair at most this far apart by the linkage's metric has been connected
+    Distance(u128),
+}
+
+/// The circuits at one cut of a [`Linkage`], numbered largest first with ties going to the one with the lowest point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Circuits {
+    /// points in each circuit, in order
+    members: Vec<Vec<usize>>,
+    circuit_of: Vec<usize>,
+}
+
+impl Circuits {
+    /// Number of circuits
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// The points in a circuit, in order
+    pub fn members(&self, circuit: usize) -> &[usize] {
+        &self.members[circuit]
+    }
+
+    /// The points in every circuit, largest first
+    pub fn iter(&self) -> impl Iterator<Item = &[usize]> + '_ {
+        self.members.iter().map(Vec::as_slice)
+    }
+
+    /// The circuit `point` belongs to
+    pub fn circuit_of(&self, point: usize) -> usize {
+        self.circuit_of[point]
+    }
+
+    /// Size of every circuit, largest first
+    pub fn sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.members.iter().map(Vec::len)
+    }
+
+    /// The `k` largest sizes
+    pub fn top_sizes(&self, k: usize) -> impl Iterator<Item = usize> + '_ {
+        self.sizes().take(k)
+    }
+
+    /// How many circuits there are of each size as `(size, count)`, largest first
+    pub fn size_distribution(&self) -> Vec<(usize, usize)> {
+        let mut distribution: Vec<(usize, usize)> = Vec::new();
+        for size in self.sizes() {
+            match distribution.last_mut() {
+                Some((last, count)) if *last == size => *count += 1,
+                _ => distribution.push((size, 1)),
+            }
+        }
+        distribution
+    }
+}
+
+/// Why the points in an input couldn't be clustered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkageError {
+    Parse(ParseError),
+    Overflow(DistanceOverflow),
+}
+
+impl From<ParseError> for LinkageError {
+    fn from(value: ParseError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+impl From<DistanceOverflow> for LinkageError {
+    fn from(value: DistanceOverflow) -> Self {
+        Self::Overflow(value)
+    }
+}
+
+impl std::fmt::Display for LinkageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "{e}"),
+            Self::Overflow(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for LinkageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e),
+            Self::Overflow(e) => Some(e),
+        }
+    }
+}
+
+/// The single-linkage hierarchy of the points in `input`, one per line
+pub fn linkage<const D: usize, T: Coord>(
+    input: &str,
+    metric: impl Metric,
+) -> Result<Linkage, LinkageError> {
+    Ok(Linkage::new(&parse::<D, T>(input)?, metric)?)
+}
+
+/// A set of connections between points and the circuits they make, to export as a graph
+#[derive(Debug, Clone)]
+pub struct Graph<'a, const D: usize, T> {
+    pub points: &'a [Point<D, T>],
+    /// `(left, right, dist)` in the order they were connected
+    pub edges: Vec<(usize, usize, u128)>,
+    pub circuits: Circuits,
+    /// What the distances are, see [`Metric::name`]
+    pub metric: &'static str,
+}
+
+impl<'a, const D: usize, T: Coord> Graph<'a, D, T> {
+    /// The first `connections` of the closest pairs, including those that were already in the same circuit
+    pub fn connections(
+        points: &'a [Point<D, T>],
+        metric: impl Metric,
+        connections: usize,
+    ) -> Result<Self, DistanceOverflow> {
+        let mut sets = UnionFind::new(points.len());
+        let name = metric.name();
+        let edges = ClosestPairs::new(points, metric)
+            .take(connections)
+            .map(|pair| {
+                let pair = pair?;
+                sets.union(pair.left, pair.right);
+                Ok((pair.left, pair.right, pair.dist))
             })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            points,
+            edges,
+            circuits: Circuits::new(&mut sets),
+            metric: name,
         })
+    }
+
+    /// The minimum spanning tree of `points`, whose hierarchy is `linkage`
+    pub fn mst(points: &'a [Point<D, T>], linkage: &Linkage) -> Self {
+        Self {
+            points,
+            edges: linkage.mst().collect(),
+            circuits: linkage.circuits(Cut::Connections(usize::MAX)),
+            metric: linkage.metric(),
+        }
+    }
+}
+
+pub mod export {
+    use std::io::{self, Write};
+
+    use super::Graph;
+    use super::point::Coord;
+
+    /// Name of a coordinate in the output
+    fn axis(axis: usize) -> String {
+        match axis {
+            0 => "x".to_string(),
+            1 => "y".to_string(),
+            2 => "z".to_string(),
+            _ => format!("c{axis}"),
+        }
+    }
+
+    /// The graph in Graphviz DOT, nodes labelled by index with their coordinates and circuit, edges with their distance
+    /// and the order they were connected in
+    ///
+    /// The distance is named after the metric, so squared euclidean distances come out as `sq_dist` and can't be
+    /// mistaken for lengths.
+    pub fn dot<const D: usize, T: Coord>(
+        graph: &Graph<D, T>,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        writeln!(out, "graph circuits {{")?;
+        for (i, point) in graph.points.iter().enumerate() {
+            write!(
+                out,
+                "  {i} [coords=\"{point}\", circuit={}",
+                graph.circuits.circuit_of(i)
+            )?;
+            for (a, coord) in point.coords().iter().enumerate() {
+                write!(out, ", {}={coord}", axis(a))?;
+            }
+            writeln!(out, "];")?;
+        }
+        for (order, &(left, right, dist)) in graph.edges.iter().enumerate() {
+            writeln!(
+                out,
+                "  {left} -- {right} [{}={dist}, order={order}];",
+                graph.metric
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// The graph in GraphML, with the same attributes as [`dot`]
+    ///
+    /// Coordinates and distances are declared as `long` when they all fit in one, and as `string` when they don't.
+    pub fn graphml<const D: usize, T: Coord>(
+        graph: &Graph<D, T>,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for a in 0..D {
+            let name = axis(a);
+            let coord_type = if graph
+                .points
+                .iter()
+                .all(|p| i64::try_from(p[a].widen()).is_ok())
+            {
+                "long"
+            } else {
+                "string"
+            };
+            writeln!(
+                out,
+                r#"  <key id="{name}" for="node" attr.name="{name}" attr.type="{coord_type}"/>"#
+            )?;
+        }
+        writeln!(
+            out,
+            r#"  <key id="circuit" for="node" attr.name="circuit" attr.type="long"/>"#
+        )?;
+        let dist_type = if graph.edges.iter().all(|&(_, _, d)| d <= i64::MAX as u128) {
+            "long"
+        } else {
+            "string"
+        };
+        writeln!(
+            out,
+            r#"  <key id="dist" for="edge" attr.name="{}" attr.type="{dist_type}"/>"#,
+            graph.metric
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="order" for="edge" attr.name="order" attr.type="long"/>"#
+        )?;
+        writeln!(out, r#"  <graph id="circuits" edgedefault="undirected">"#)?;
+        for (i, point) in graph.points.iter().enumerate() {
+            writeln!(out, r#"    <node id="n{i}">"#)?;
+            for (a, coord) in point.coords().iter().enumerate() {
+                writeln!(out, r#"      <data key="{}">{coord}</data>"#, axis(a))?;
+            }
+            writeln!(
+                out,
+                r#"      <data key="circuit">{}</data>"#,
+                graph.circuits.circuit_of(i)
+            )?;
+            writeln!(out, "    </node>")?;
+        }
+        for (order, &(left, right, dist)) in graph.edges.iter().enumerate() {
+            writeln!(
+                out,
+                r#"    <edge id="e{order}" source="n{left}" target="n{right}">"#
+            )?;
+            writeln!(out, r#"      <data key="dist">{dist}</data>"#)?;
+            writeln!(out, r#"      <data key="order">{order}</data>"#)?;
+            writeln!(out, "    </edge>")?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+}
+
+/// A line of the input that isn't a point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Counting from 1
+    pub line: usize,
+    pub error: TryFromStrError,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Every point in the order they show up, one per line
+///
+/// Each line is its own junction box even when another one has the same coordinates, the two of them are just no
+/// distance apart and get connected before anything else. Everything after this works with indices into the result.
+pub fn parse<const D: usize, T: Coord>(input: &str) -> Result<Vec<Point<D, T>>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| l.parse().map_err(|error| ParseError { line: i + 1, error }))
         .collect()
 }
 
+/// The junction boxes in the puzzle input
+fn points(input: &str) -> Vec<Vec3> {
+    parse(input).unwrap_or_else(|e| panic!("Malformed input! {e}"))
+}
+
 fn part_1(input: &str, num_conns: usize) -> i32 {
-    let pairs = parse(input);
-    let mut circuits: Vec<Circuit> = Vec::new();
-    for v in pairs.into_iter().take(num_conns) {
-        let (left, right) = v.to_pair();
-        let encountered = circuits
-            .extract_if(.., |c| c.nodes.contains(&left) || c.nodes.contains(&right))
-            .fold(HashSet::from([left, right]), |acc, c| {
-                c.nodes.union(&acc).copied().collect()
-            });
+    let points = points(input);
+    let mut circuits = UnionFind::new(points.len());
+    for v in ClosestPairs::new(&points, SqEuclidean).take(num_conns) {
+        let (left, right) = v.unwrap_or_else(|e| panic!("{e}")).to_pair();
+        circuits.union(left, right);
+    }
 
-        circuits.push(Circuit { nodes: encountered });
+    let mut sizes: Vec<_> = circuits.sizes().collect();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    sizes.iter().take(3).map(|&size| size as i32).product()
+}
+
+/// Why part 2 has no answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinError {
+    /// A line of the input isn't a junction box
+    Parse(ParseError),
+    /// With fewer than two junction boxes there's never a connection that joins them all
+    TooFewPoints(usize),
+    /// The product of the last two x coordinates doesn't fit in a `u64`
+    Overflow,
+    /// Some junction boxes are too far apart to tell which pairs are closest
+    TooFarApart(DistanceOverflow),
+}
+
+impl From<ParseError> for JoinError {
+    fn from(value: ParseError) -> Self {
+        Self::Parse(value)
     }
+}
 
-    circuits.sort();
-    circuits
-        .iter()
-        .take(3)
-        .map(|c| c.nodes.len() as i32)
-        .product()
+impl From<DistanceOverflow> for JoinError {
+    fn from(value: DistanceOverflow) -> Self {
+        Self::TooFarApart(value)
+    }
 }
 
-fn part_2(input: &str, input_len: usize) -> u64 {
-    let mut pairs = parse(input);
-    let mut circuits: Vec<Circuit> = Vec::new();
-    let (left, right) = loop {
-        let v = pairs.pop_first().unwrap();
-        let (left, right) = v.to_pair();
-        let encountered = circuits
-            .extract_if(.., |c| c.nodes.contains(&left) || c.nodes.contains(&right))
-            .fold(HashSet::from([left, right]), |acc, c| {
-                c.nodes.union(&acc).copied().collect()
-            });
+impl std::fmt::Display for JoinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "{e}"),
+            Self::TooFewPoints(n) => write!(
+                f,
+                "Only {n} junction box(es), at least 2 are needed to make a last connection"
+            ),
+            Self::Overflow => write!(f, "Product of the x coordinates overflows a u64"),
+            Self::TooFarApart(e) => write!(f, "{e}"),
+        }
+    }
+}
 
-        circuits.push(Circuit { nodes: encountered });
-        let i = circuits.iter().map(|c| c.nodes.len()).sum::<usize>();
-        if i >= input_len {
-            break v.to_pair();
+impl std::error::Error for JoinError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e),
+            Self::TooFarApart(e) => Some(e),
+            Self::TooFewPoints(_) | Self::Overflow => None,
         }
-    };
+    }
+}
 
-    left.x() * right.x()
+fn part_2(input: &str) -> Result<u64, JoinError> {
+    let points: Vec<Vec3> = parse(input)?;
+    // the last merge of the whole hierarchy is the one that leaves a single circuit
+    let linkage = Linkage::new(&points, SqEuclidean)?;
+    let last = linkage
+        .merges()
+        .last()
+        .filter(|m| m.size == points.len())
+        .ok_or(JoinError::TooFewPoints(points.len()))?;
+
+    points[last.left]
+        .x()
+        .checked_mul(points[last.right].x())
+        .ok_or(JoinError::Overflow)
 }
 
 #[cfg(test)]
 mod test {
+    use super::metric::{Chebyshev, Manhattan};
     use super::*;
+    use crate::rng::Rng;
     #[test]
     fn part_1_example() {
         const INPUT: &str = "162,817,812\n57,618,57\n906,360,560\n592,479,940\n352,342,300\n466,668,158\n542,29,236\n431,825,988\n739,650,466\n52,470,668\n216,146,977\n819,987,18\n117,168,530\n805,96,715\n346,949,466\n970,615,88\n941,993,340\n862,61,35\n984,92,344\n425,690,689";
@@ -193,7 +1099,551 @@ mod test {
     fn part_2_example() {
         const INPUT: &str = "162,817,812\n57,618,57\n906,360,560\n592,479,940\n352,342,300\n466,668,158\n542,29,236\n431,825,988\n739,650,466\n52,470,668\n216,146,977\n819,987,18\n117,168,530\n805,96,715\n346,949,466\n970,615,88\n941,993,340\n862,61,35\n984,92,344\n425,690,689";
 
-        let answer = part_2(INPUT, 20);
-        assert_eq!(answer, 25272)
+        let answer = part_2(INPUT);
+        assert_eq!(answer, Ok(25272))
+    }
+
+    #[test]
+    fn union_find() {
+        let mut sets = UnionFind::new(6);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(2, 3));
+        assert!(sets.union(1, 3));
+        assert!(!sets.union(0, 2));
+        assert_eq!(sets.size_of(3), 4);
+        assert_eq!(sets.find(0), sets.find(2));
+        assert_ne!(sets.find(0), sets.find(4));
+        let mut sizes: Vec<_> = sets.sizes().collect();
+        sizes.sort();
+        assert_eq!(sizes, [1, 1, 4]);
+    }
+
+    #[test]
+    fn tied_distances() {
+        // a unit square has 4 sides at the same distance and 2 diagonals at another, plus 3 points far from it
+        const INPUT: &str = "0,0,0\n1,0,0\n0,1,0\n1,1,0\n100,0,0\n0,100,0\n0,0,100";
+        let points = parse::<3, u64>(INPUT).unwrap();
+        let pairs: Vec<_> = ClosestPairs::new(&points, SqEuclidean)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(pairs.len(), points.len() * (points.len() - 1) / 2);
+        let first: Vec<_> = pairs.iter().take(6).map(VecPair::to_pair).collect();
+        assert_eq!(first, [(0, 1), (0, 2), (1, 3), (2, 3), (0, 3), (1, 2)]);
+
+        // the 4 sides alone join the whole square
+        assert_eq!(part_1(INPUT, 4), 4);
+        assert_eq!(part_1(INPUT, 3), 4);
+        assert_eq!(part_1(INPUT, 2), 3);
+    }
+
+    #[test]
+    fn tied_distances_reproducible() {
+        const INPUT: &str = "0,0,0\n2,0,0\n0,2,0\n2,2,0\n1,1,5\n9,9,9";
+        let lines: Vec<_> = INPUT.lines().collect();
+        let expected = part_1(INPUT, 5);
+        for rotation in 0..lines.len() {
+            let mut rotated = lines.clone();
+            rotated.rotate_left(rotation);
+            let input = rotated.join("\n");
+            // the same tied pairs always get taken, whatever order the points come in
+            assert_eq!(part_1(&input, 4), 4);
+            assert_eq!(part_1(&input, 5), expected);
+        }
+    }
+
+    /// every pair sorted up front, the way `parse` used to do it
+    fn all_pairs<const D: usize, T: Coord>(
+        points: &[Point<D, T>],
+        metric: &impl Metric,
+    ) -> Vec<VecPair> {
+        let mut pairs: Vec<_> = (0..points.len())
+            .flat_map(|left| {
+                (left + 1..points.len()).map(move |right| VecPair {
+                    dist: metric.dist(&points[left], &points[right]).unwrap(),
+                    left,
+                    right,
+                })
+            })
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// `n` points with coordinates below `max`
+    fn random_points(n: usize, max: u64, seed: u64) -> String {
+        let mut rng = Rng::new(seed);
+        (0..n)
+            .map(|_| format!("{},{},{}", rng.below(max), rng.below(max), rng.below(max)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn closest_pairs_match_brute_force() {
+        // small coordinates so there are plenty of ties
+        for (n, max, seed) in [
+            (1, 10, 1),
+            (2, 10, 2),
+            (30, 4, 3),
+            (120, 10, 4),
+            (200, 1000, 5),
+        ] {
+            let points = parse::<3, u64>(&random_points(n, max, seed)).unwrap();
+            let lazy: Vec<_> = ClosestPairs::new(&points, SqEuclidean)
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(
+                lazy,
+                all_pairs(&points, &SqEuclidean),
+                "{n} points below {max}"
+            );
+        }
+    }
+
+    #[test]
+    fn closest_pairs_prefix() {
+        let points = parse::<3, u64>(&random_points(1500, 100_000, 6)).unwrap();
+        let lazy: Vec<_> = ClosestPairs::new(&points, SqEuclidean)
+            .take(3000)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(lazy, all_pairs(&points, &SqEuclidean)[..3000]);
+    }
+
+    /// total length of the minimum spanning tree by Prim's algorithm over every pair
+    fn prim_weight(points: &[Vec3]) -> u128 {
+        let mut best = vec![u128::MAX; points.len()];
+        let mut done = vec![false; points.len()];
+        let mut total = 0;
+        best[0] = 0;
+        for _ in 0..points.len() {
+            let next = (0..points.len())
+                .filter(|&i| !done[i])
+                .min_by_key(|&i| best[i])
+                .unwrap();
+            done[next] = true;
+            total += best[next];
+            for i in 0..points.len() {
+                best[i] = best[i].min(points[next].sq_dist(&points[i]).unwrap());
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn linkage_example() {
+        const INPUT: &str = "162,817,812\n57,618,57\n906,360,560\n592,479,940\n352,342,300\n466,668,158\n542,29,236\n431,825,988\n739,650,466\n52,470,668\n216,146,977\n819,987,18\n117,168,530\n805,96,715\n346,949,466\n970,615,88\n941,993,340\n862,61,35\n984,92,344\n425,690,689";
+        let points = parse::<3, u64>(INPUT).unwrap();
+        let linkage = linkage::<3, u64>(INPUT, SqEuclidean).unwrap();
+        assert_eq!(linkage.len(), 20);
+        assert_eq!(linkage.merges().len(), 19);
+
+        // part 2 is the last edge of the tree
+        let last = linkage.merges().last().unwrap();
+        assert_eq!(last.size, 20);
+        assert_eq!(points[last.left].x() * points[last.right].x(), 25272);
+        assert_eq!(last.circuits.0.max(last.circuits.1), 20 + 17);
+
+        // the first 10 connections only make 9 merges, one of them was inside a circuit
+        let cut = linkage.cut_connections(10);
+        assert_eq!(cut.len(), 9);
+        let mut circuits = UnionFind::new(20);
+        for m in cut {
+            circuits.union(m.left, m.right);
+        }
+        let mut sizes: Vec<_> = circuits.sizes().collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(sizes[..3].iter().product::<usize>(), 40);
+
+        assert_eq!(linkage.cut_distance(cut[8].dist), cut);
+        assert!(linkage.cut_distance(0).is_empty());
+        assert_eq!(linkage.cut_distance(u128::MAX), linkage.merges());
+    }
+
+    #[test]
+    fn linkage_is_minimum_spanning_tree() {
+        for (n, max, seed) in [(2, 10, 7), (40, 5, 8), (150, 1000, 9)] {
+            let points = parse::<3, u64>(&random_points(n, max, seed)).unwrap();
+            let linkage = Linkage::new(&points, SqEuclidean).unwrap();
+            assert_eq!(linkage.merges().len(), points.len() - 1);
+            assert_eq!(
+                linkage.mst().map(|(_, _, d)| d).sum::<u128>(),
+                prim_weight(&points)
+            );
+
+            // every circuit is merged away exactly once, apart from the last one
+            let mut used = vec![false; 2 * points.len() - 1];
+            for (k, m) in linkage.merges().iter().enumerate() {
+                for c in [m.circuits.0, m.circuits.1] {
+                    assert!(c < points.len() + k && !used[c]);
+                    used[c] = true;
+                }
+            }
+            assert_eq!(used.iter().filter(|&&u| !u).count(), 1);
+        }
+    }
+
+    #[test]
+    fn circuit_queries() {
+        // two squares of side 1 and 2, with a point off on its own
+        const INPUT: &str = "0,0,0\n1,0,0\n0,1,0\n1,1,0\n10,0,0\n12,0,0\n10,2,0\n12,2,0\n50,50,50";
+        let linkage = linkage::<3, u64>(INPUT, SqEuclidean).unwrap();
+
+        let circuits = linkage.circuits(Cut::Distance(1));
+        assert_eq!(circuits.len(), 6);
+        assert_eq!(circuits.members(0), [0, 1, 2, 3]);
+        assert_eq!(circuits.size_distribution(), [(4, 1), (1, 5)]);
+        assert_eq!(circuits.circuit_of(3), 0);
+        assert_eq!(circuits.circuit_of(8), 5);
+
+        let circuits = linkage.circuits(Cut::Distance(4));
+        assert_eq!(
+            circuits.iter().collect::<Vec<_>>(),
+            [&[0, 1, 2, 3][..], &[4, 5, 6, 7], &[8]]
+        );
+        assert_eq!(circuits.top_sizes(2).collect::<Vec<_>>(), [4, 4]);
+        assert_eq!(circuits.circuit_of(6), 1);
+
+        assert_eq!(linkage.circuits(Cut::Connections(0)).len(), 9);
+        assert_eq!(linkage.circuits(Cut::Distance(u128::MAX)).len(), 1);
+        assert!(
+            Linkage::new::<3, u64>(&[], SqEuclidean)
+                .unwrap()
+                .circuits(Cut::Connections(3))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn circuit_queries_match_part_1() {
+        let input = random_points(300, 1000, 10);
+        let linkage = linkage::<3, u64>(&input, SqEuclidean).unwrap();
+        for connections in [0, 1, 10, 100, 250, 1000] {
+            let circuits = linkage.circuits(Cut::Connections(connections));
+            let top: usize = circuits.top_sizes(3).product();
+            assert_eq!(top as i32, part_1(&input, connections), "{connections}");
+            assert_eq!(circuits.sizes().sum::<usize>(), 300);
+        }
+    }
+
+    #[test]
+    fn points_parse() {
+        let p: Point<3, i64> = "-3,0,7".parse().unwrap();
+        assert_eq!((p.x(), p.y(), p.z()), (-3, 0, 7));
+        let q: Point<2, i32> = "5,-1".parse().unwrap();
+        assert_eq!(q.coords(), &[5, -1]);
+        assert_eq!(q.sq_dist(&Point::new([-1, 7])), Some(100));
+        let r: Point<4, u8> = "1,2,3,4".parse().unwrap();
+        assert_eq!(r[3], 4);
+
+        assert!(matches!(
+            "1,2".parse::<Vec3>(),
+            Err(TryFromStrError::WrongDimension {
+                expected: 3,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            "1,2,3,4".parse::<Vec3>(),
+            Err(TryFromStrError::WrongDimension { found: 4, .. })
+        ));
+        assert!(matches!(
+            "1,-2,3".parse::<Vec3>(),
+            Err(TryFromStrError::NotInts(_))
+        ));
+    }
+
+    #[test]
+    fn linkage_any_dimension() {
+        // the example moved so half of it is negative gives the same tree
+        let input = random_points(80, 1000, 11);
+        let shifted: Vec<_> = input
+            .lines()
+            .map(|l| {
+                let p: Point<3, i64> = l.parse().unwrap();
+                format!("{},{},{}", p.x() - 500, p.y() - 500, p.z() - 500)
+            })
+            .collect();
+        let shifted = linkage::<3, i64>(&shifted.join("\n"), SqEuclidean).unwrap();
+        assert_eq!(shifted, linkage::<3, u64>(&input, SqEuclidean).unwrap());
+
+        // two lines along a plane, and along a fourth axis
+        let flat = linkage::<2, i32>("0,0\n0,1\n0,2\n-5,0\n-5,-1", SqEuclidean).unwrap();
+        assert_eq!(
+            flat.mst().collect::<Vec<_>>(),
+            [(0, 1, 1), (1, 2, 1), (3, 4, 1), (0, 3, 25)]
+        );
+        let deep = linkage::<4, i8>("0,0,0,0\n0,0,0,-3\n0,0,0,3\n0,0,0,-10", SqEuclidean).unwrap();
+        assert_eq!(
+            deep.mst().collect::<Vec<_>>(),
+            [(0, 1, 9), (0, 2, 9), (1, 3, 49)]
+        );
+    }
+
+    #[test]
+    fn metrics() {
+        let a: Point<3, i64> = Point::new([1, -2, 3]);
+        let b = Point::new([-3, 0, 3]);
+        assert_eq!(SqEuclidean.dist(&a, &b), Some(20));
+        assert_eq!(Manhattan.dist(&a, &b), Some(6));
+        assert_eq!(Chebyshev.dist(&a, &b), Some(4));
+
+        // way past what a u64 can hold
+        let min = Point::new([i64::MIN; 3]);
+        let max = Point::new([i64::MAX; 3]);
+        assert_eq!(SqEuclidean.dist(&min, &max), None);
+        assert_eq!(Manhattan.dist(&min, &max), Some(3 * u64::MAX as u128));
+        assert_eq!(Chebyshev.dist(&min, &max), Some(u64::MAX as u128));
+        let far = Point::new([0, 0, 1 << 40]);
+        assert_eq!(SqEuclidean.dist(&Vec3::new([0; 3]), &far), Some(1 << 80));
+    }
+
+    #[test]
+    fn closest_pairs_any_metric() {
+        for (n, max, seed) in [(40, 4, 12), (150, 1000, 13)] {
+            let points = parse::<3, u64>(&random_points(n, max, seed)).unwrap();
+            let lazy: Vec<_> = ClosestPairs::new(&points, Manhattan)
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(lazy, all_pairs(&points, &Manhattan));
+            let lazy: Vec<_> = ClosestPairs::new(&points, Chebyshev)
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(lazy, all_pairs(&points, &Chebyshev));
+        }
+
+        // a knight's move is further than two steps along one axis by Manhattan, closer by euclidean distance
+        const INPUT: &str = "0,0,0\n2,1,0\n0,0,3";
+        let points = parse::<3, u64>(INPUT).unwrap();
+        let mst: Vec<_> = Linkage::new(&points, SqEuclidean).unwrap().mst().collect();
+        assert_eq!(mst, [(0, 1, 5), (0, 2, 9)]);
+        let mst: Vec<_> = Linkage::new(&points, Manhattan).unwrap().mst().collect();
+        assert_eq!(mst, [(0, 1, 3), (0, 2, 3)]);
+    }
+
+    #[test]
+    fn huge_coordinates() {
+        // spaced out so the squared distances need more than 64 bits
+        let step = 1u64 << 40;
+        let input: Vec<_> = [0, 1, 3, 7]
+            .iter()
+            .map(|&i| format!("{},0,{}", i * step, u64::MAX - i * step))
+            .collect();
+        let linkage = linkage::<3, u64>(&input.join("\n"), SqEuclidean).unwrap();
+        let mst: Vec<_> = linkage.mst().map(|(l, r, _)| (l, r)).collect();
+        assert_eq!(mst, [(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(linkage.merges()[0].dist, 2 << 80);
+    }
+
+    #[test]
+    fn part_2_duplicates() {
+        // the repeated box joins its twin first, but it still has to be joined
+        const INPUT: &str = "1,0,0\n2,0,0\n1,0,0\n10,0,0";
+        let points = parse::<3, u64>(INPUT).unwrap();
+        assert_eq!(points.len(), 4);
+        let first = ClosestPairs::new(&points, SqEuclidean)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!((first.to_pair(), first.dist), ((0, 2), 0));
+        assert_eq!(part_2(INPUT), Ok(20));
+        assert_eq!(part_2("3,0,0\n3,0,0"), Ok(9));
+    }
+
+    #[test]
+    fn part_2_never_joined() {
+        assert_eq!(part_2(""), Err(JoinError::TooFewPoints(0)));
+        assert_eq!(part_2("1,2,3"), Err(JoinError::TooFewPoints(1)));
+        let huge = format!("{0},0,0\n{0},1,0", u64::MAX);
+        assert_eq!(part_2(&huge), Err(JoinError::Overflow));
+
+        let error = part_2("1,2,3\n4,5").unwrap_err();
+        assert!(matches!(
+            error,
+            JoinError::Parse(ParseError { line: 2, .. })
+        ));
+        assert_eq!(
+            error.to_string(),
+            "line 2: Expected 3 comma seperated values, found 2"
+        );
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(source.to_string(), error.to_string());
+    }
+
+    #[test]
+    fn export_connections() {
+        const INPUT: &str = "0,0,0\n1,0,0\n0,2,0\n9,9,9";
+        let points = parse::<3, u64>(INPUT).unwrap();
+        let graph = Graph::connections(&points, SqEuclidean, 2).unwrap();
+        assert_eq!(graph.edges, [(0, 1, 1), (0, 2, 4)]);
+
+        let mut out = Vec::new();
+        export::dot(&graph, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "graph circuits {
+  0 [coords=\"0,0,0\", circuit=0, x=0, y=0, z=0];
+  1 [coords=\"1,0,0\", circuit=0, x=1, y=0, z=0];
+  2 [coords=\"0,2,0\", circuit=0, x=0, y=2, z=0];
+  3 [coords=\"9,9,9\", circuit=1, x=9, y=9, z=9];
+  0 -- 1 [sq_dist=1, order=0];
+  0 -- 2 [sq_dist=4, order=1];
+}
+"
+        );
+
+        let mut out = Vec::new();
+        export::graphml(&graph, &mut out).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+        assert!(xml.contains(r#"<key id="z" for="node" attr.name="z" attr.type="long"/>"#));
+        assert!(
+            xml.contains(r#"<key id="dist" for="edge" attr.name="sq_dist" attr.type="long"/>"#)
+        );
+        assert!(xml.contains(
+            "<node id=\"n3\">\n      <data key=\"x\">9</data>\n      <data key=\"y\">9</data>\n      \
+             <data key=\"z\">9</data>\n      <data key=\"circuit\">1</data>\n    </node>"
+        ));
+        assert!(xml.contains(
+            "<edge id=\"e1\" source=\"n0\" target=\"n2\">\n      <data key=\"dist\">4</data>\n      \
+             <data key=\"order\">1</data>\n    </edge>"
+        ));
+        assert_eq!(xml.matches("<node ").count(), 4);
+        assert_eq!(xml.matches("<edge ").count(), 2);
+        assert!(xml.ends_with("</graphml>\n"));
+    }
+
+    #[test]
+    fn export_mst() {
+        let points = parse::<2, i32>("0,0\n-1,0\n5,5\n0,3\n0,3").unwrap();
+        let linkage = Linkage::new(&points, Manhattan).unwrap();
+        let graph = Graph::mst(&points, &linkage);
+        assert_eq!(graph.edges.len(), 4);
+        assert_eq!(graph.circuits.len(), 1);
+
+        let mut out = Vec::new();
+        export::dot(&graph, &mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains("  1 [coords=\"-1,0\", circuit=0, x=-1, y=0];\n"));
+        assert!(dot.contains("  3 -- 4 [manhattan_dist=0, order=0];\n"));
+        assert!(dot.contains("  0 -- 1 [manhattan_dist=1, order=1];\n"));
+        assert!(dot.contains("  2 -- 3 [manhattan_dist=7, order=3];\n"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = parse::<3, u64>("1,2,3\n4,5,6\n7,8\n9,9,9").unwrap_err();
+        assert_eq!(
+            error,
+            ParseError {
+                line: 3,
+                error: TryFromStrError::WrongDimension {
+                    expected: 3,
+                    found: 2
+                }
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "line 3: Expected 3 comma seperated values, found 2"
+        );
+
+        let error = parse::<3, u64>("1,2,3\n4,x,6").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.error, TryFromStrError::NotInts(_)));
+        assert_eq!(
+            error.to_string(),
+            "line 2: Coordinate not an int: invalid digit found in string"
+        );
+        let source = std::error::Error::source(&error).unwrap();
+        assert!(source.source().is_some());
+
+        assert!(linkage::<3, u64>("1,2,3\n\n4,5,6", SqEuclidean).is_err());
+        assert_eq!(parse::<3, u64>("").unwrap(), []);
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2: Coordinate not an int")]
+    fn part_1_malformed() {
+        part_1("1,2,3\n-4,5,6", 1);
+    }
+
+    #[test]
+    fn distance_overflow() {
+        // only the two corners can't be measured, and the tree never needs them
+        let points: Vec<Point<3, i64>> = vec![
+            Point::new([i64::MIN; 3]),
+            Point::new([0; 3]),
+            Point::new([1; 3]),
+            Point::new([i64::MAX; 3]),
+        ];
+        let tree = Linkage::new(&points, SqEuclidean).unwrap();
+        assert_eq!(tree.merges().last().unwrap().dist, 3 << 126);
+        assert!(Graph::connections(&points, SqEuclidean, 5).is_ok());
+        // the sixth connection is the corners
+        assert_eq!(
+            Graph::connections(&points, SqEuclidean, 6).unwrap_err(),
+            DistanceOverflow
+        );
+        assert_eq!(
+            Linkage::new(&points, Manhattan)
+                .unwrap()
+                .merges()
+                .last()
+                .unwrap()
+                .dist,
+            3 * (u64::MAX as u128 / 2 + 1)
+        );
+
+        // and here they're all the tree has
+        assert_eq!(
+            Linkage::new(&[points[0], points[3]], SqEuclidean),
+            Err(DistanceOverflow)
+        );
+        assert!(Linkage::new(&[points[0], points[3]], Chebyshev).is_ok());
+        let input = format!("{0},{0},{0}\n0,0,0", u64::MAX);
+        assert!(matches!(
+            linkage::<3, u64>(&input, SqEuclidean),
+            Err(LinkageError::Overflow(DistanceOverflow))
+        ));
+        let error = part_2(&input).unwrap_err();
+        assert_eq!(error, JoinError::TooFarApart(DistanceOverflow));
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(linkage::<3, u64>(&input, Manhattan).is_ok());
+    }
+
+    #[test]
+    fn distance_overflow_only_on_real_pairs() {
+        // the box around these is too big to measure corner to corner, but every pair of points fits
+        let x = 12912720851596686090u64;
+        let input = format!("0,0,0\n{x},0,0\n0,{x},0\n0,0,{x}");
+        let points = parse::<3, u64>(&input).unwrap();
+        let lazy: Vec<_> = ClosestPairs::new(&points, SqEuclidean)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(lazy, all_pairs(&points, &SqEuclidean));
+        let linkage = Linkage::new(&points, SqEuclidean).unwrap();
+        assert_eq!(
+            linkage.mst().collect::<Vec<_>>(),
+            [0, 1, 2].map(|i| (0, i + 1, (x as u128).pow(2)))
+        );
+    }
+
+    #[test]
+    fn export_huge_distances() {
+        let input = format!("0,0,0\n{},0,0", u64::MAX);
+        let points = parse::<3, u64>(&input).unwrap();
+        let graph = Graph::connections(&points, SqEuclidean, 1).unwrap();
+        let mut out = Vec::new();
+        export::graphml(&graph, &mut out).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+        assert!(xml.contains(r#"attr.name="sq_dist" attr.type="string"/>"#));
+        // only x is too big for a long
+        assert!(xml.contains(r#"<key id="x" for="node" attr.name="x" attr.type="string"/>"#));
+        assert!(xml.contains(r#"<key id="y" for="node" attr.name="y" attr.type="long"/>"#));
+        assert!(xml.contains(&format!(r#"<data key="x">{}</data>"#, u64::MAX)));
+        assert!(xml.contains(&format!(
+            r#"<data key="dist">{}</data>"#,
+            (u64::MAX as u128).pow(2)
+        )));
     }
 }
@@ -0,0 +1,397 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+/// Offsets of the 8 cells surrounding a cell, row by row
+const MOORE: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// Offsets of the 4 cells sharing an edge with a cell
+const VON_NEUMANN: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+/// What lies beyond the edges of a grid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Edges {
+    /// nothing, offsets off the grid don't land anywhere
+    #[default]
+    Empty,
+    /// something, offsets off the grid don't land on a cell but a caller can count them as filled
+    Full,
+    /// the grid wraps around into a torus
+    Wrap,
+    /// the grid is reflected at its edges, the edge cell included so the cell just off the edge is the edge cell
+    Mirror,
+}
+
+impl Edges {
+    /// Map an axis position that may be off the grid back onto an axis of length `len`
+    fn fold(self, x: isize, len: usize) -> Option<usize> {
+        let n = len as isize;
+        match self {
+            _ if (0..n).contains(&x) => Some(x as usize),
+            Self::Empty | Self::Full => None,
+            _ if len == 0 => None,
+            Self::Wrap => Some(x.rem_euclid(n) as usize),
+            Self::Mirror => {
+                let m = x.rem_euclid(2 * n);
+                Some(if m < n { m } else { 2 * n - 1 - m } as usize)
+            }
+        }
+    }
+
+    /// Every position within `lo..=hi`, on or off the grid, that folds back to `x` on an axis of length `len`
+    fn images(self, x: usize, len: usize, lo: isize, hi: isize) -> Vec<isize> {
+        let (x, n) = (x as isize, len as isize);
+        // the first image at or above `lo` in each class of positions `period` apart
+        let from = |start: isize, period: isize| {
+            let first = lo + (start - lo).rem_euclid(period);
+            (first..=hi).step_by(period as usize)
+        };
+        match self {
+            Self::Empty | Self::Full => vec![x],
+            Self::Wrap => from(x, n).collect(),
+            Self::Mirror => from(x, 2 * n).chain(from(2 * n - 1 - x, 2 * n)).collect(),
+        }
+    }
+}
+
+/// A heap backed `width` x `height` grid stored row by row, indexed by `(row, column)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Self {
+            cells: vec![fill; width * height],
+            width,
+            height,
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// Build a grid from cells laid out row by row, `None` if there aren't exactly `width * height` of them
+    pub fn from_vec(width: usize, height: usize, cells: Vec<T>) -> Option<Self> {
+        (cells.len() == width * height).then_some(Self {
+            cells,
+            width,
+            height,
+        })
+    }
+
+    /// Parse one row per line, converting every byte with `f`
+    ///
+    /// Every line has to be as long as the first one.
+    pub fn parse(input: &str, mut f: impl FnMut(u8) -> T) -> Result<Self, ParseGridError> {
+        let width = input.lines().next().map_or(0, str::len);
+        let mut cells = Vec::with_capacity(input.len());
+        let mut height = 0;
+        for (i, line) in input.lines().enumerate() {
+            if line.len() != width {
+                return Err(ParseGridError::RaggedLine {
+                    line: i + 1,
+                    expected: width,
+                    found: line.len(),
+                });
+            }
+            cells.extend(line.bytes().map(&mut f));
+            height += 1;
+        }
+        Ok(Self {
+            cells,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn in_bounds(&self, row: usize, col: usize) -> bool {
+        row < self.height && col < self.width
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        self.in_bounds(row, col)
+            .then(|| &self.cells[row * self.width + col])
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        self.in_bounds(row, col)
+            .then(|| &mut self.cells[row * self.width + col])
+    }
+
+    /// # Safety
+    /// `row` and `col` must be in bounds, see [`Grid::in_bounds`]
+    pub unsafe fn get_unchecked(&self, row: usize, col: usize) -> &T {
+        unsafe { self.cells.get_unchecked(row * self.width + col) }
+    }
+
+    /// # Safety
+    /// `row` and `col` must be in bounds, see [`Grid::in_bounds`]
+    pub unsafe fn get_unchecked_mut(&mut self, row: usize, col: usize) -> &mut T {
+        unsafe { self.cells.get_unchecked_mut(row * self.width + col) }
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.cells[row * self.width..(row + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &[T]> {
+        // a grid 0 wide still has `height` rows, they're just empty
+        (0..self.height).map(|row| self.row(row))
+    }
+
+    pub fn column(&self, col: usize) -> impl ExactSizeIterator<Item = &T> {
+        assert!(col < self.width, "Column {col} out of bounds!");
+        (0..self.height).map(move |row| &self.cells[row * self.width + col])
+    }
+
+    pub fn columns(&self) -> impl ExactSizeIterator<Item = impl ExactSizeIterator<Item = &T>> {
+        (0..self.width).map(|col| self.column(col))
+    }
+
+    /// Every `(row, column)` in the grid, row by row
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> + use<T> {
+        let width = self.width;
+        (0..self.height).flat_map(move |row| (0..width).map(move |col| (row, col)))
+    }
+
+    /// The in bounds cells out of the 8 surrounding `(row, col)`
+    pub fn neighbours(
+        &self,
+        row: usize,
+        col: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + use<T> {
+        self.stencil(row, col, &MOORE)
+    }
+
+    /// The in bounds cells out of the 4 sharing an edge with `(row, col)`
+    pub fn orthogonal_neighbours(
+        &self,
+        row: usize,
+        col: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + use<T> {
+        self.stencil(row, col, &VON_NEUMANN)
+    }
+
+    /// The in bounds cells at each `(row, column)` offset from `(row, col)`
+    pub fn stencil<'a>(
+        &self,
+        row: usize,
+        col: usize,
+        offsets: &'a [(isize, isize)],
+    ) -> impl Iterator<Item = (usize, usize)> + use<'a, T> {
+        let (width, height) = (self.width, self.height);
+        offsets.iter().filter_map(move |&(di, dj)| {
+            let i = row.checked_add_signed(di)?;
+            let j = col.checked_add_signed(dj)?;
+            (i < height && j < width).then_some((i, j))
+        })
+    }
+
+    /// Where `(row, col)` moved by `(di, dj)` lands once `edges` are accounted for, `None` if it's off the grid
+    pub fn resolve(
+        &self,
+        row: usize,
+        col: usize,
+        (di, dj): (isize, isize),
+        edges: Edges,
+    ) -> Option<(usize, usize)> {
+        let i = edges.fold(row as isize + di, self.height)?;
+        let j = edges.fold(col as isize + dj, self.width)?;
+        Some((i, j))
+    }
+
+    /// Every cell with `(row, col)` in its `offsets` neighbourhood under `edges`, once for each offset that lands on it
+    ///
+    /// With [`Edges::Empty`] and [`Edges::Full`] these are just the cells at the opposite offsets, but wrapping and
+    /// mirroring can reach `(row, col)` through any of its images off the grid.
+    pub fn reverse_stencil(
+        &self,
+        row: usize,
+        col: usize,
+        offsets: &[(isize, isize)],
+        edges: Edges,
+    ) -> impl Iterator<Item = (usize, usize)> + use<T> {
+        let (width, height) = (self.width, self.height);
+        let reach = |axis: fn(&(isize, isize)) -> isize| {
+            offsets.iter().map(axis).map(isize::abs).max().unwrap_or(0)
+        };
+        let (ri, rj) = (reach(|o| o.0), reach(|o| o.1));
+        let rows = edges.images(row, height, -ri, height as isize - 1 + ri);
+        let cols = edges.images(col, width, -rj, width as isize - 1 + rj);
+
+        let mut cells = Vec::new();
+        for &(di, dj) in offsets {
+            for &i in &rows {
+                for &j in &cols {
+                    let (qi, qj) = (i - di, j - dj);
+                    if (0..height as isize).contains(&qi) && (0..width as isize).contains(&qj) {
+                        cells.push((qi as usize, qj as usize));
+                    }
+                }
+            }
+        }
+        cells.into_iter()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.cells.iter_mut()
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        self.get(row, col)
+            .unwrap_or_else(|| panic!("Cell ({row}, {col}) out of bounds!"))
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut Self::Output {
+        self.get_mut(row, col)
+            .unwrap_or_else(|| panic!("Cell ({row}, {col}) out of bounds!"))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseGridError {
+    /// a line (counting from 1) that isn't as long as the first line
+    RaggedLine {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ParseGridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RaggedLine {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line} is {found} cells long, expected {expected} like the first line"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseGridError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_rectangle() {
+        let grid = Grid::parse("abc\ndef\n", |b| b).unwrap();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid[(1, 2)], b'f');
+        assert_eq!(grid.get(2, 0), None);
+        assert_eq!(grid.get(0, 3), None);
+        assert_eq!(grid.row(1), b"def");
+        assert_eq!(grid.rows().collect::<Vec<_>>(), [b"abc", b"def"]);
+        assert_eq!(grid.column(1).copied().collect::<Vec<_>>(), b"be");
+        assert_eq!(grid.columns().len(), 3);
+        assert_eq!(unsafe { *grid.get_unchecked(1, 0) }, b'd');
+    }
+
+    #[test]
+    fn parse_empty_lines() {
+        let grid = Grid::parse("\n\n\n", |b| b).unwrap();
+        assert_eq!((grid.width(), grid.height()), (0, 3));
+        let rows = grid.rows();
+        assert_eq!(rows.len(), 3);
+        assert!(rows.into_iter().all(<[u8]>::is_empty));
+        assert_eq!(grid.columns().len(), 0);
+    }
+
+    #[test]
+    fn parse_ragged() {
+        assert_eq!(
+            Grid::parse("abc\nde\nfgh", |b| b),
+            Err(ParseGridError::RaggedLine {
+                line: 2,
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn neighbours_in_bounds() {
+        let grid = Grid::new(4, 3, 0u8);
+        assert_eq!(grid.neighbours(0, 0).count(), 3);
+        assert_eq!(grid.neighbours(1, 1).count(), 8);
+        assert_eq!(grid.neighbours(2, 3).count(), 3);
+        assert_eq!(
+            grid.orthogonal_neighbours(0, 3).collect::<Vec<_>>(),
+            [(0, 2), (1, 3)]
+        );
+        assert_eq!(grid.positions().count(), 12);
+    }
+
+    #[test]
+    fn resolve_edges() {
+        let grid = Grid::new(3, 2, 0u8);
+        assert_eq!(grid.resolve(0, 0, (-1, -1), Edges::Empty), None);
+        assert_eq!(grid.resolve(0, 0, (-1, -1), Edges::Full), None);
+        assert_eq!(grid.resolve(0, 0, (-1, -1), Edges::Wrap), Some((1, 2)));
+        assert_eq!(grid.resolve(0, 0, (-1, -1), Edges::Mirror), Some((0, 0)));
+        assert_eq!(grid.resolve(1, 2, (1, 2), Edges::Mirror), Some((1, 1)));
+        assert_eq!(grid.resolve(1, 2, (0, -2), Edges::Wrap), Some((1, 0)));
+    }
+
+    #[test]
+    fn reverse_stencil_inverts_resolve() {
+        let offsets = [(-1, -1), (-1, 0), (0, 1), (1, 1), (2, -1), (0, 3)];
+        for edges in [Edges::Empty, Edges::Full, Edges::Wrap, Edges::Mirror] {
+            for (width, height) in [(1, 1), (1, 4), (2, 3), (5, 4)] {
+                let grid = &Grid::new(width, height, 0u8);
+                for (row, col) in grid.positions() {
+                    let mut expected: Vec<_> = grid
+                        .positions()
+                        .flat_map(|q| {
+                            offsets
+                                .iter()
+                                .filter(move |&&o| {
+                                    grid.resolve(q.0, q.1, o, edges) == Some((row, col))
+                                })
+                                .map(move |_| q)
+                        })
+                        .collect();
+                    let mut found: Vec<_> =
+                        grid.reverse_stencil(row, col, &offsets, edges).collect();
+                    expected.sort();
+                    found.sort();
+                    assert_eq!(found, expected, "{edges:?} {width}x{height} ({row}, {col})");
+                }
+            }
+        }
+    }
+}
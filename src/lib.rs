@@ -6,3 +6,10 @@ pub mod day03;
 pub mod day04;
 pub mod day05;
 pub mod day08;
+pub mod grid;
+pub mod parallel;
+
+#[cfg(test)]
+mod counting;
+#[cfg(test)]
+mod rng;
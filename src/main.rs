@@ -1,19 +1,55 @@
+use std::io::{self, BufWriter};
+
+use aoc2025::day05::{self, Report, Reversed};
+
 fn main() {
-    #[cfg(feature = "input")]
-    {
-        use aoc2025::*;
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-        // println!("Part 1: {}", day01::solve_part_1());
-        // println!("Part 2: {}", day01::solve_part_2());
-        // println!("Part 1: {}", day02::solve_part_1());
-        // println!("Part 2: {}", day02::solve_part_2());
-        // println!("Part 1: {}", day03::solve_part_1());
-        // println!("Part 2: {}", day03::solve_part_2());
-        // println!("Part 1: {}", day04::solve_part_1());
-        // println!("Part 2: {}", day04::solve_part_2());
-        // println!("Part 1: {}", day05::solve_part_1());
-        println!("Part 2: {}", day05::solve_part_2());
-        // println!("Part 1: {}", day08::solve_part_1());
-        // println!("Part 2: {}", day08::solve_part_2());
+    // day 5 ranges and IDs on stdin, for ID lists far too big to hold in memory
+    if args.first().is_some_and(|flag| flag == "--stream") {
+        let report = if args.iter().any(|arg| arg == "--summary") {
+            Report::Summary
+        } else {
+            Report::Each
+        };
+        let out = BufWriter::new(io::stdout().lock());
+        // wide enough for negative IDs and anything up to `u64::MAX`
+        let summary = day05::stream::<i128>(io::stdin().lock(), out, report, Reversed::Reject)
+            .expect("Failed to stream IDs!");
+        // keep stdout to one line per ID unless that's all we're printing
+        let counts = format!("Fresh: {}, Spoiled: {}", summary.fresh, summary.spoiled);
+        match report {
+            Report::Each => eprintln!("{counts}"),
+            Report::Summary => println!("{counts}"),
+        }
+    } else {
+        #[cfg(feature = "input")]
+        solve(&args);
     }
 }
+
+#[cfg(feature = "input")]
+fn solve(args: &[String]) {
+    use aoc2025::*;
+
+    if let [flag, dir] = args
+        && flag == "--render"
+    {
+        let rounds = day04::render_part_2(dir.as_ref()).expect("Failed to write day 4 frames!");
+        println!("Wrote {rounds} frames to {dir}");
+        return;
+    }
+
+    // println!("Part 1: {}", day01::solve_part_1());
+    // println!("Part 2: {}", day01::solve_part_2());
+    // println!("Part 1: {}", day02::solve_part_1());
+    // println!("Part 2: {}", day02::solve_part_2());
+    // println!("Part 1: {}", day03::solve_part_1());
+    // println!("Part 2: {}", day03::solve_part_2());
+    // println!("Part 1: {}", day04::solve_part_1());
+    // println!("Part 2: {}", day04::solve_part_2());
+    // println!("Part 1: {}", day05::solve_part_1());
+    println!("Part 2: {}", day05::solve_part_2());
+    // println!("Part 1: {}", day08::solve_part_1());
+    // println!("Part 2: {}", day08::solve_part_2().unwrap());
+}
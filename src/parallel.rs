@@ -0,0 +1,115 @@
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// Inputs shorter than this many bytes aren't worth spawning threads for
+pub const DEFAULT_THRESHOLD: usize = 64 * 1024;
+
+/// Opt-in multi-threaded execution for days whose input is a list of independent items
+///
+/// The input is split at separator boundaries into one chunk per thread, each chunk is solved on its own
+/// [`thread::scope`] worker and the results are reduced on the calling thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parallel {
+    pub threads: NonZeroUsize,
+    pub threshold: usize,
+}
+
+impl Default for Parallel {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+impl Parallel {
+    pub fn new(threads: NonZeroUsize, threshold: usize) -> Self {
+        Self { threads, threshold }
+    }
+
+    /// Split `input` into at most one non-empty chunk per thread, ending each chunk just before a `sep`
+    ///
+    /// Inputs under the threshold come back as a single chunk so they stay on one thread.
+    pub fn split<'a>(&self, input: &'a str, sep: u8) -> Vec<&'a str> {
+        let threads = self.threads.get();
+        if threads == 1 || input.len() < self.threshold {
+            return vec![input];
+        }
+
+        let bytes = input.as_bytes();
+        let target = input.len().div_ceil(threads);
+        let mut chunks = Vec::with_capacity(threads);
+        let mut start = 0;
+        while start < bytes.len() {
+            let end = match bytes[(start + target).min(bytes.len())..]
+                .iter()
+                .position(|&b| b == sep)
+            {
+                Some(offset) => (start + target).min(bytes.len()) + offset,
+                None => bytes.len(),
+            };
+            // `sep` is ascii so both ends are char boundaries
+            if end > start {
+                chunks.push(&input[start..end]);
+            }
+            start = end + 1;
+        }
+        chunks
+    }
+
+    /// Run `f` over every item on its own scoped thread, keeping the results in order
+    pub fn run<I: Sync, T: Send>(&self, items: &[I], f: impl Fn(&I) -> T + Sync) -> Vec<T> {
+        if let [item] = items {
+            return vec![f(item)];
+        }
+        thread::scope(|s| {
+            let handles: Vec<_> = items.iter().map(|item| s.spawn(|| f(item))).collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Worker thread panicked!"))
+                .collect()
+        })
+    }
+
+    /// Solve each chunk of `input` with `map` and combine the results with `reduce`
+    pub fn map_reduce<T: Send + Default>(
+        &self,
+        input: &str,
+        sep: u8,
+        map: impl Fn(&str) -> T + Sync,
+        reduce: impl Fn(T, T) -> T,
+    ) -> T {
+        self.run(&self.split(input, sep), |chunk| map(chunk))
+            .into_iter()
+            .reduce(reduce)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_on_boundaries() {
+        let parallel = Parallel::new(NonZeroUsize::new(3).unwrap(), 0);
+        let input = "aa\nbbbb\nc\ndd\neeeee\nf";
+        let chunks = parallel.split(input, b'\n');
+        assert!(chunks.len() <= 3);
+        assert_eq!(chunks.join("\n"), input);
+        assert!(chunks.iter().all(|c| !c.is_empty()));
+    }
+
+    #[test]
+    fn split_below_threshold() {
+        let parallel = Parallel::new(NonZeroUsize::new(8).unwrap(), 1024);
+        assert_eq!(parallel.split("1,2,3", b','), ["1,2,3"]);
+    }
+
+    #[test]
+    fn split_more_threads_than_items() {
+        let parallel = Parallel::new(NonZeroUsize::new(16).unwrap(), 0);
+        assert_eq!(parallel.split("1,2,3,", b','), ["1", "2", "3"]);
+    }
+}
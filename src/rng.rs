@@ -0,0 +1,25 @@
+//! Seeded random numbers for tests
+
+/// Marsaglia's xorshift, nowhere near good enough for anything but reproducible test data
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// `seed` can't be 0, xorshift never leaves it
+    pub fn new(seed: u64) -> Self {
+        assert_ne!(seed, 0, "xorshift needs a non-zero seed");
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}